@@ -1,11 +1,38 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use nalgebra_glm::vec2;
-use rasterization_in_a_weekend::rasterization::rasterize_solid_triangle;
+use rasterization_in_a_weekend::{rasterization::rasterize_solid_triangle, viewport::Rect};
 
 pub fn triangle_rasterization_benchmarks(c: &mut Criterion) {
     let vertices = [vec2(480.0, 180.0), vec2(160.0, 90.0), vec2(160.0, 270.0)];
+    let bounds = Rect::new(0, 0, 640, 360);
     c.bench_function("barycentric", |b| {
-        b.iter(|| rasterize_solid_triangle(black_box(&vertices), black_box(|_| {})))
+        b.iter(|| {
+            rasterize_solid_triangle(black_box(&vertices), black_box(&bounds), black_box(|_| {}))
+        })
+    });
+    c.bench_function("barycentric_fragments", |b| {
+        b.iter(|| {
+            rasterize_solid_triangle(black_box(&vertices), black_box(&bounds), |fragment| {
+                black_box(fragment);
+            })
+        })
+    });
+    let thin = [vec2(10.0, 10.0), vec2(630.0, 352.0), vec2(631.0, 350.0)];
+    c.bench_function("thin_triangle", |b| {
+        b.iter(|| {
+            rasterize_solid_triangle(black_box(&thin), black_box(&bounds), |fragment| {
+                black_box(fragment);
+            })
+        })
+    });
+
+    let large = [vec2(0.0, 0.0), vec2(0.0, 720.0), vec2(1280.0, 0.0)];
+    c.bench_function("large_triangle", |b| {
+        b.iter(|| {
+            rasterize_solid_triangle(black_box(&large), black_box(&bounds), |fragment| {
+                black_box(fragment);
+            })
+        })
     });
 }
 
@@ -1,17 +1,41 @@
 use nalgebra_glm::Vec4;
 
-use crate::vertex::Vertex;
-
-pub fn clip_triangle(input_vertices: &[Vertex; 3]) -> Vec<Vertex> {
-    const PLANES: [Vec4; 6] = [
-        Vec4::new(1.0, 0.0, 0.0, 1.0),
-        Vec4::new(-1.0, 0.0, 0.0, 1.0),
-        Vec4::new(0.0, 1.0, 0.0, 1.0),
-        Vec4::new(0.0, -1.0, 0.0, 1.0),
-        Vec4::new(0.0, 0.0, 1.0, 0.0),
-        Vec4::new(0.0, 0.0, -1.0, 1.0),
-    ];
+use crate::{varyings::Varyings, vertex::ClipVertex};
 
+const PLANES: [Vec4; 6] = [
+    Vec4::new(1.0, 0.0, 0.0, 1.0),
+    Vec4::new(-1.0, 0.0, 0.0, 1.0),
+    Vec4::new(0.0, 1.0, 0.0, 1.0),
+    Vec4::new(0.0, -1.0, 0.0, 1.0),
+    Vec4::new(0.0, 0.0, 1.0, 0.0),
+    Vec4::new(0.0, 0.0, -1.0, 1.0),
+];
+
+pub fn clip_point<V: Varyings>(vertex: &ClipVertex<V>) -> Option<ClipVertex<V>> {
+    PLANES
+        .iter()
+        .all(|plane| vertex.position.dot(plane) >= 0.0)
+        .then_some(*vertex)
+}
+
+pub fn clip_line<V: Varyings>(vertices: &[ClipVertex<V>; 2]) -> Option<[ClipVertex<V>; 2]> {
+    let [mut v0, mut v1] = *vertices;
+    for plane in PLANES {
+        let distance_0 = v0.position.dot(&plane);
+        let distance_1 = v1.position.dot(&plane);
+        if distance_0 < 0.0 && distance_1 < 0.0 {
+            return None;
+        }
+        if distance_0 < 0.0 {
+            v0 = v0.lerp(&v1, distance_0 / (distance_0 - distance_1));
+        } else if distance_1 < 0.0 {
+            v1 = v1.lerp(&v0, distance_1 / (distance_1 - distance_0));
+        }
+    }
+    Some([v0, v1])
+}
+
+pub fn clip_triangle<V: Varyings>(input_vertices: &[ClipVertex<V>; 3]) -> Vec<ClipVertex<V>> {
     // const WEIGHTS: [Vec3; 3] = [
     //     Vec3::new(1.0, 0.0, 0.0),
     //     Vec3::new(0.0, 1.0, 0.0),
@@ -19,13 +43,13 @@ pub fn clip_triangle(input_vertices: &[Vertex; 3]) -> Vec<Vertex> {
     // ];
 
     let mut buffer_vertices = Vec::with_capacity(3 + PLANES.len());
-    buffer_vertices.extend(input_vertices.into_iter().copied());
+    buffer_vertices.extend(input_vertices.iter().copied());
     let mut input_vertices = Vec::new();
     for plane in PLANES {
         input_vertices.clone_from(&buffer_vertices);
         buffer_vertices.clear();
 
-        if input_vertices.len() == 0 {
+        if input_vertices.is_empty() {
             return vec![];
         }
 
@@ -34,8 +58,8 @@ pub fn clip_triangle(input_vertices: &[Vertex; 3]) -> Vec<Vertex> {
             let vertex_j = input_vertices[j];
             let vertex_i = input_vertices[i];
 
-            let distance_j = vertex_j.coords.dot(&plane);
-            let distance_i = vertex_i.coords.dot(&plane);
+            let distance_j = vertex_j.position.dot(&plane);
+            let distance_i = vertex_i.position.dot(&plane);
             let alpha = distance_j / (distance_j - distance_i);
             let intersection = vertex_j.lerp(&vertex_i, alpha);
 
@@ -50,7 +74,40 @@ pub fn clip_triangle(input_vertices: &[Vertex; 3]) -> Vec<Vertex> {
             j = i;
         }
     }
-    return buffer_vertices;
+    buffer_vertices
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra_glm::vec4;
+
+    use super::*;
+
+    #[test]
+    fn lines_are_clipped_against_each_plane() {
+        let line = [
+            ClipVertex::new(vec4(-2.0, 0.0, 0.5, 1.0), 0.0),
+            ClipVertex::new(vec4(0.0, 0.0, 0.5, 1.0), 1.0),
+        ];
+
+        assert_eq!(
+            clip_line(&line),
+            Some([
+                ClipVertex::new(vec4(-1.0, 0.0, 0.5, 1.0), 0.5),
+                ClipVertex::new(vec4(0.0, 0.0, 0.5, 1.0), 1.0),
+            ])
+        );
+    }
+
+    #[test]
+    fn lines_fully_outside_are_discarded() {
+        let line = [
+            ClipVertex::new(vec4(0.0, 0.0, -1.0, 1.0), ()),
+            ClipVertex::new(vec4(0.5, 0.5, -0.5, 1.0), ()),
+        ];
+
+        assert_eq!(clip_line(&line), None);
+    }
 }
 
 // #[cfg(test)]
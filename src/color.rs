@@ -1,27 +1,94 @@
 #![allow(unused)]
 
-use nalgebra_glm::Vec3;
+use std::sync::LazyLock;
 
-pub type Color = Vec3;
+use nalgebra_glm::Vec4;
 
-pub const BLACK: Color = Color::new(0.0, 0.0, 0.0);
-pub const WHITE: Color = Color::new(1.0, 1.0, 1.0);
-pub const RED: Color = Color::new(1.0, 0.0, 0.0);
-pub const GREEN: Color = Color::new(0.0, 1.0, 0.0);
-pub const BLUE: Color = Color::new(0.0, 0.0, 1.0);
+/// Linear RGBA color, with straight (non-premultiplied) alpha.
+pub type Color = Vec4;
+
+pub const TRANSPARENT: Color = Color::new(0.0, 0.0, 0.0, 0.0);
+pub const BLACK: Color = Color::new(0.0, 0.0, 0.0, 1.0);
+pub const WHITE: Color = Color::new(1.0, 1.0, 1.0, 1.0);
+pub const RED: Color = Color::new(1.0, 0.0, 0.0, 1.0);
+pub const GREEN: Color = Color::new(0.0, 1.0, 0.0, 1.0);
+pub const BLUE: Color = Color::new(0.0, 0.0, 1.0, 1.0);
 
 pub fn to_raw_color(color: Color) -> u32 {
     let r = (color.x.clamp(0.0, 1.0) * 255.999) as u32;
     let g = (color.y.clamp(0.0, 1.0) * 255.999) as u32;
     let b = (color.z.clamp(0.0, 1.0) * 255.999) as u32;
-    (r << 16) | (g << 8) | b
+    let a = (color.w.clamp(0.0, 1.0) * 255.999) as u32;
+    (a << 24) | (r << 16) | (g << 8) | b
 }
 
 pub fn from_raw_color(raw: u32) -> Color {
     let r = ((raw >> 16) & 0xFF) as f32 / 255.0;
     let g = ((raw >> 8) & 0xFF) as f32 / 255.0;
     let b = (raw & 0xFF) as f32 / 255.0;
-    Color::new(r, g, b)
+    let a = ((raw >> 24) & 0xFF) as f32 / 255.0;
+    Color::new(r, g, b, a)
+}
+
+/// How the color channels of 8-bit images and framebuffers relate to linear intensities. Alpha is
+/// always stored linearly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorSpace {
+    /// Channels are proportional to intensity. Used for data such as normal maps.
+    #[default]
+    Linear,
+    /// Channels are encoded with the sRGB transfer function, like most images and displays.
+    Srgb,
+}
+
+impl ColorSpace {
+    /// Decodes a raw `0xAARRGGBB` value to a linear color.
+    pub fn decode(self, raw: u32) -> Color {
+        match self {
+            ColorSpace::Linear => from_raw_color(raw),
+            ColorSpace::Srgb => {
+                let channel = |shift: u32| SRGB_TO_LINEAR[((raw >> shift) & 0xFF) as usize];
+                Color::new(
+                    channel(16),
+                    channel(8),
+                    channel(0),
+                    ((raw >> 24) & 0xFF) as f32 / 255.0,
+                )
+            }
+        }
+    }
+
+    /// Encodes a linear color to a raw `0xAARRGGBB` value.
+    pub fn encode(self, color: Color) -> u32 {
+        match self {
+            ColorSpace::Linear => to_raw_color(color),
+            ColorSpace::Srgb => to_raw_color(Color::new(
+                linear_to_srgb(color.x),
+                linear_to_srgb(color.y),
+                linear_to_srgb(color.z),
+                color.w,
+            )),
+        }
+    }
+}
+
+static SRGB_TO_LINEAR: LazyLock<[f32; 256]> =
+    LazyLock::new(|| std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.0)));
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
 }
 
 #[cfg(test)]
@@ -30,19 +97,34 @@ mod tests {
 
     #[test]
     pub fn color_to_raw() {
-        assert_eq!(to_raw_color(BLACK), 0x00000000);
-        assert_eq!(to_raw_color(WHITE), 0x00FFFFFF);
-        assert_eq!(to_raw_color(RED), 0x00FF0000);
-        assert_eq!(to_raw_color(GREEN), 0x0000FF00);
-        assert_eq!(to_raw_color(BLUE), 0x000000FF);
+        assert_eq!(to_raw_color(TRANSPARENT), 0x00000000);
+        assert_eq!(to_raw_color(BLACK), 0xFF000000);
+        assert_eq!(to_raw_color(WHITE), 0xFFFFFFFF);
+        assert_eq!(to_raw_color(RED), 0xFFFF0000);
+        assert_eq!(to_raw_color(GREEN), 0xFF00FF00);
+        assert_eq!(to_raw_color(BLUE), 0xFF0000FF);
     }
 
     #[test]
     pub fn raw_to_color() {
-        assert_eq!(from_raw_color(0x00000000), BLACK);
-        assert_eq!(from_raw_color(0x00FFFFFF), WHITE);
-        assert_eq!(from_raw_color(0x00FF0000), RED);
-        assert_eq!(from_raw_color(0x0000FF00), GREEN);
-        assert_eq!(from_raw_color(0x000000FF), BLUE);
+        assert_eq!(from_raw_color(0x00000000), TRANSPARENT);
+        assert_eq!(from_raw_color(0xFF000000), BLACK);
+        assert_eq!(from_raw_color(0xFFFFFFFF), WHITE);
+        assert_eq!(from_raw_color(0xFFFF0000), RED);
+        assert_eq!(from_raw_color(0xFF00FF00), GREEN);
+        assert_eq!(from_raw_color(0xFF0000FF), BLUE);
+    }
+
+    #[test]
+    pub fn srgb_round_trips_every_byte() {
+        for byte in 0..=255u32 {
+            let raw = 0x80000000 | byte << 16 | byte << 8 | byte;
+            assert_eq!(ColorSpace::Srgb.encode(ColorSpace::Srgb.decode(raw)), raw);
+        }
+        assert!((ColorSpace::Srgb.decode(0xFFBCBCBC).x - 0.5).abs() < 0.003);
+        assert_eq!(
+            ColorSpace::Srgb.encode(Color::new(0.5, 0.0, 1.0, 0.5)),
+            0x7FBC00FF
+        );
     }
 }
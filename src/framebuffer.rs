@@ -1,52 +1,250 @@
-use std::ops::RangeBounds;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
 
+use half::f16;
+#[cfg(feature = "window")]
 use minifb::Window;
 
 use crate::{
-    color::{from_raw_color, to_raw_color, Color},
-    image::map_coords_to_index,
+    color::{Color, ColorSpace},
+    image::{map_coords_to_index, Image},
+    multisample::SampleCount,
+    state::CompareOp,
+    tonemap::ToneMapping,
+    viewport::Rect,
 };
 
+/// Storage format of a framebuffer's color attachment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorFormat {
+    /// 8 bits per channel packed as `0xAARRGGBB`, clamped to [0, 1] and encoded in the
+    /// framebuffer's color space.
+    #[default]
+    Rgba8,
+    /// Linear half-precision floats, which can exceed 1.0.
+    Rgba16F,
+    /// Linear single-precision floats, which can exceed 1.0.
+    Rgba32F,
+}
+
+enum ColorAttachment {
+    Rgba8(Vec<u32>),
+    Rgba16F(Vec<[f16; 4]>),
+    Rgba32F(Vec<Color>),
+}
+
+impl ColorAttachment {
+    fn new(format: ColorFormat, size: usize) -> Self {
+        match format {
+            ColorFormat::Rgba8 => ColorAttachment::Rgba8(vec![0; size]),
+            ColorFormat::Rgba16F => ColorAttachment::Rgba16F(vec![[f16::ZERO; 4]; size]),
+            ColorFormat::Rgba32F => ColorAttachment::Rgba32F(vec![Color::zeros(); size]),
+        }
+    }
+
+    fn format(&self) -> ColorFormat {
+        match self {
+            ColorAttachment::Rgba8(_) => ColorFormat::Rgba8,
+            ColorAttachment::Rgba16F(_) => ColorFormat::Rgba16F,
+            ColorAttachment::Rgba32F(_) => ColorFormat::Rgba32F,
+        }
+    }
+
+    fn get(&self, index: usize, color_space: ColorSpace) -> Color {
+        match self {
+            ColorAttachment::Rgba8(samples) => color_space.decode(samples[index]),
+            ColorAttachment::Rgba16F(samples) => Color::from(samples[index].map(f16::to_f32)),
+            ColorAttachment::Rgba32F(samples) => samples[index],
+        }
+    }
+
+    fn as_mut(&mut self) -> ColorAttachmentMut<'_> {
+        match self {
+            ColorAttachment::Rgba8(samples) => ColorAttachmentMut::Rgba8(samples),
+            ColorAttachment::Rgba16F(samples) => ColorAttachmentMut::Rgba16F(samples),
+            ColorAttachment::Rgba32F(samples) => ColorAttachmentMut::Rgba32F(samples),
+        }
+    }
+}
+
+enum ColorAttachmentMut<'a> {
+    Rgba8(&'a mut [u32]),
+    Rgba16F(&'a mut [[f16; 4]]),
+    Rgba32F(&'a mut [Color]),
+}
+
+impl<'a> ColorAttachmentMut<'a> {
+    fn get(&self, index: usize, color_space: ColorSpace) -> Color {
+        match self {
+            ColorAttachmentMut::Rgba8(samples) => color_space.decode(samples[index]),
+            ColorAttachmentMut::Rgba16F(samples) => Color::from(samples[index].map(f16::to_f32)),
+            ColorAttachmentMut::Rgba32F(samples) => samples[index],
+        }
+    }
+
+    fn set(&mut self, index: usize, color: Color, color_space: ColorSpace) {
+        match self {
+            ColorAttachmentMut::Rgba8(samples) => samples[index] = color_space.encode(color),
+            ColorAttachmentMut::Rgba16F(samples) => samples[index] = to_half(color),
+            ColorAttachmentMut::Rgba32F(samples) => samples[index] = color,
+        }
+    }
+
+    fn fill(&mut self, color: Color, color_space: ColorSpace) {
+        match self {
+            ColorAttachmentMut::Rgba8(samples) => samples.fill(color_space.encode(color)),
+            ColorAttachmentMut::Rgba16F(samples) => samples.fill(to_half(color)),
+            ColorAttachmentMut::Rgba32F(samples) => samples.fill(color),
+        }
+    }
+
+    fn split(self, chunk_size: usize) -> Vec<ColorAttachmentMut<'a>> {
+        match self {
+            ColorAttachmentMut::Rgba8(samples) => samples
+                .chunks_mut(chunk_size)
+                .map(ColorAttachmentMut::Rgba8)
+                .collect(),
+            ColorAttachmentMut::Rgba16F(samples) => samples
+                .chunks_mut(chunk_size)
+                .map(ColorAttachmentMut::Rgba16F)
+                .collect(),
+            ColorAttachmentMut::Rgba32F(samples) => samples
+                .chunks_mut(chunk_size)
+                .map(ColorAttachmentMut::Rgba32F)
+                .collect(),
+        }
+    }
+}
+
+fn to_half(color: Color) -> [f16; 4] {
+    [color.x, color.y, color.z, color.w].map(f16::from_f32)
+}
+
+/// Reconstruction filter used to downsample a supersampled framebuffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DownsampleFilter {
+    /// Averages the block of pixels covered by each destination pixel.
+    #[default]
+    Box,
+    /// Weights pixels linearly by their distance, over twice the width of the box.
+    Tent,
+}
+
+/// Color, depth and stencil attachments, with every sample of a pixel stored next to each other.
+/// Accessors that take only coordinates address the first sample of the pixel. Colors are read
+/// and written as linear values and stored in the framebuffer's color format and space.
 pub struct Framebuffer {
-    color_attachment: Vec<u32>,
+    color_attachment: ColorAttachment,
+    color_space: ColorSpace,
     depth_attachment: Vec<f32>,
+    stencil_attachment: Vec<u8>,
     width: usize,
     height: usize,
+    samples: SampleCount,
 }
 
 impl Framebuffer {
     pub fn new(width: usize, height: usize) -> Self {
+        Self::new_multisampled(width, height, SampleCount::X1)
+    }
+
+    pub fn new_multisampled(width: usize, height: usize, samples: SampleCount) -> Self {
+        let size = width * height * samples.count();
         Self {
-            color_attachment: vec![0; width * height],
-            depth_attachment: vec![std::f32::INFINITY; width * height],
+            color_attachment: ColorAttachment::new(ColorFormat::Rgba8, size),
+            color_space: ColorSpace::Linear,
+            depth_attachment: vec![f32::INFINITY; size],
+            stencil_attachment: vec![0; size],
             width,
             height,
+            samples,
         }
     }
 
+    /// Replaces the color attachment with a cleared one in the given format. Floating-point
+    /// formats keep colors above 1.0 for [`Framebuffer::tone_map`].
+    pub fn with_color_format(mut self, format: ColorFormat) -> Self {
+        let size = self.width * self.height * self.samples.count();
+        self.color_attachment = ColorAttachment::new(format, size);
+        self
+    }
+
+    pub fn color_format(&self) -> ColorFormat {
+        self.color_attachment.format()
+    }
+
+    /// Sets the color space 8-bit colors are encoded in, without converting the current
+    /// contents. Floating-point formats are always linear.
+    pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
     pub fn clear(&mut self, color: Color, depth: f32) {
-        self.color_attachment.fill(to_raw_color(color));
+        self.color_attachment.as_mut().fill(color, self.color_space);
         self.depth_attachment.fill(depth);
     }
 
-    pub fn test_and_set_depth_safe(&mut self, coords: (usize, usize), depth: f32) -> bool {
+    pub fn clear_stencil(&mut self, stencil: u8) {
+        self.stencil_attachment.fill(stencil);
+    }
+
+    pub fn test_and_set_depth_safe(
+        &mut self,
+        coords: (usize, usize),
+        depth: f32,
+        compare_op: CompareOp,
+        write_enable: bool,
+    ) -> bool {
         if !self.contains(coords) {
             return false;
         }
-        return self.test_and_set_depth(coords, depth);
+        self.test_and_set_depth(coords, depth, compare_op, write_enable)
     }
 
-    pub fn test_and_set_depth(&mut self, coords: (usize, usize), depth: f32) -> bool {
-        let target = &mut self.depth_attachment[map_coords_to_index(coords, self.width)];
-        if depth < *target {
+    pub fn test_and_set_depth(
+        &mut self,
+        coords: (usize, usize),
+        depth: f32,
+        compare_op: CompareOp,
+        write_enable: bool,
+    ) -> bool {
+        let index = self.index(coords, 0);
+        let target = &mut self.depth_attachment[index];
+        if !compare_op.test(depth, *target) {
+            return false;
+        }
+        if write_enable {
             *target = depth;
-            return true;
         }
-        return false;
+        true
+    }
+
+    pub fn get_depth(&self, coords: (usize, usize)) -> f32 {
+        self.depth_attachment[self.index(coords, 0)]
+    }
+
+    pub fn get_stencil(&self, coords: (usize, usize)) -> u8 {
+        self.stencil_attachment[self.index(coords, 0)]
+    }
+
+    pub fn set_stencil(&mut self, coords: (usize, usize), stencil: u8) {
+        let index = self.index(coords, 0);
+        self.stencil_attachment[index] = stencil;
     }
 
     pub fn set_color(&mut self, coords: (usize, usize), color: Color) {
-        self.color_attachment[map_coords_to_index(coords, self.width)] = to_raw_color(color);
+        let index = self.index(coords, 0);
+        self.color_attachment
+            .as_mut()
+            .set(index, color, self.color_space);
     }
 
     pub fn set_color_safe(&mut self, coords: (usize, usize), color: Color) {
@@ -57,12 +255,191 @@ impl Framebuffer {
     }
 
     pub fn get_color(&self, coords: (usize, usize)) -> Color {
-        from_raw_color(self.color_attachment[map_coords_to_index(coords, self.width)])
+        self.color_attachment
+            .get(self.index(coords, 0), self.color_space)
+    }
+
+    pub fn get_sample_color(&self, coords: (usize, usize), sample: usize) -> Color {
+        self.color_attachment
+            .get(self.index(coords, sample), self.color_space)
+    }
+
+    pub fn get_sample_depth(&self, coords: (usize, usize), sample: usize) -> f32 {
+        self.depth_attachment[self.index(coords, sample)]
+    }
+
+    /// Averages the samples of each pixel into the color attachment of a single-sampled
+    /// framebuffer of the same size. Samples are averaged in linear space and encoded in the color
+    /// space of `destination`.
+    pub fn resolve(&self, destination: &mut Framebuffer) {
+        self.resolve_with(destination, |color| color);
+    }
+
+    /// Resolves an HDR framebuffer like [`Framebuffer::resolve`], mapping the averaged colors
+    /// into [0, 1] for an 8-bit destination.
+    pub fn tone_map(&self, destination: &mut Framebuffer, tone_mapping: ToneMapping) {
+        self.resolve_with(destination, |color| tone_mapping.apply(color));
+    }
+
+    fn resolve_with(&self, destination: &mut Framebuffer, map: impl Fn(Color) -> Color) {
+        assert_eq!(destination.samples, SampleCount::X1);
+        assert_eq!(
+            (self.width, self.height),
+            (destination.width, destination.height)
+        );
+        let color_space = destination.color_space;
+        let mut target = destination.color_attachment.as_mut();
+        for i in 0..self.width * self.height {
+            target.set(i, map(self.pixel_color(i)), color_space);
+        }
+    }
+
+    /// Filters the color of a framebuffer rendered at an integer multiple of the size of
+    /// `destination` down into it, for supersampling. Multisampled pixels are resolved first.
+    /// Panics unless the size of `self` is the same non-zero multiple of the size of
+    /// `destination` in both dimensions.
+    pub fn downsample(&self, destination: &mut Framebuffer, filter: DownsampleFilter) {
+        assert_eq!(destination.samples, SampleCount::X1);
+        let factor = self.width.checked_div(destination.width).unwrap_or(0);
+        assert!(
+            factor > 0
+                && (self.width, self.height)
+                    == (destination.width * factor, destination.height * factor),
+            "cannot downsample a {}x{} framebuffer to {}x{}",
+            self.width,
+            self.height,
+            destination.width,
+            destination.height
+        );
+        let radius = match filter {
+            DownsampleFilter::Box => factor as f32 / 2.0,
+            DownsampleFilter::Tent => factor as f32,
+        };
+        let weight = |distance: f32| match filter {
+            DownsampleFilter::Box => 1.0,
+            DownsampleFilter::Tent => 1.0 - distance / radius,
+        };
+        // Source pixels within the footprint of a destination pixel, with their weights along one
+        // axis.
+        let taps = |center: f32, size: usize| {
+            let first = (center - radius).floor().max(0.0) as usize;
+            let last = ((center + radius).ceil() as usize).min(size);
+            (first..last).filter_map(move |i| {
+                let distance = (i as f32 + 0.5 - center).abs();
+                (distance < radius).then(|| (i, weight(distance)))
+            })
+        };
+
+        for y in 0..destination.height {
+            let center_y = (y as f32 + 0.5) * factor as f32;
+            for x in 0..destination.width {
+                let center_x = (x as f32 + 0.5) * factor as f32;
+                let mut sum = Color::zeros();
+                let mut total_weight = 0.0;
+                for (source_y, weight_y) in taps(center_y, self.height) {
+                    for (source_x, weight_x) in taps(center_x, self.width) {
+                        let weight = weight_x * weight_y;
+                        let pixel = map_coords_to_index((source_x, source_y), self.width);
+                        sum += self.pixel_color(pixel) * weight;
+                        total_weight += weight;
+                    }
+                }
+                destination.color_attachment.as_mut().set(
+                    map_coords_to_index((x, y), destination.width),
+                    sum / total_weight,
+                    destination.color_space,
+                );
+            }
+        }
+    }
+
+    /// Raw color samples, packed as `0xAARRGGBB` in the framebuffer's color space, with the
+    /// samples of each pixel next to each other in row-major pixel order. Panics for
+    /// floating-point formats.
+    pub fn color_attachment(&self) -> &[u32] {
+        match &self.color_attachment {
+            ColorAttachment::Rgba8(samples) => samples,
+            _ => panic!(
+                "{:?} framebuffers have no 8-bit color attachment",
+                self.color_format()
+            ),
+        }
     }
 
+    /// Depth samples, laid out like [`Framebuffer::color_attachment`].
+    pub fn depth_attachment(&self) -> &[f32] {
+        &self.depth_attachment
+    }
+
+    /// Copies the color attachment into an image in the same color space, resolving
+    /// multisampled pixels. Floating-point formats give HDR images.
+    pub fn to_image(&self) -> Image {
+        let colors = (0..self.width * self.height).map(|i| self.pixel_color(i));
+        if self.color_format() != ColorFormat::Rgba8 {
+            return Image::from_hdr_buffer(colors.collect(), self.width, self.height);
+        }
+        let buffer = colors.map(|color| self.color_space.encode(color)).collect();
+        Image::from_buffer(buffer, self.width, self.height).with_color_space(self.color_space)
+    }
+
+    /// Saves the resolved color attachment, in the format given by the extension of `path`.
+    pub fn save_color(&self, path: impl AsRef<Path>) -> image::ImageResult<()> {
+        self.to_image().save(path)
+    }
+
+    /// Depth of the first sample of each pixel as a grayscale image, with the nearest finite
+    /// depth mapped to black and the farthest to white. Infinite depths are white.
+    pub fn depth_image(&self) -> Image {
+        let depths = || self.depth_attachment.iter().step_by(self.samples.count());
+        let (min, max) = depths()
+            .filter(|depth| depth.is_finite())
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &depth| {
+                (min.min(depth), max.max(depth))
+            });
+        let range = (max - min).max(f32::EPSILON);
+        let buffer = depths()
+            .map(|&depth| {
+                let value = if depth.is_finite() {
+                    (depth - min) / range
+                } else {
+                    1.0
+                };
+                ColorSpace::Linear.encode(Color::new(value, value, value, 1.0))
+            })
+            .collect();
+        Image::from_buffer(buffer, self.width, self.height)
+    }
+
+    /// Saves the depth as a normalized grayscale image; see [`Framebuffer::depth_image`].
+    pub fn save_depth(&self, path: impl AsRef<Path>) -> image::ImageResult<()> {
+        self.depth_image().save(path)
+    }
+
+    /// Saves the depth of the first sample of each pixel as a grayscale PFM file, keeping the
+    /// full 32-bit float values.
+    pub fn save_depth_pfm(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_depth_pfm(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn write_depth_pfm(&self, mut writer: impl Write) -> io::Result<()> {
+        // A negative scale marks little-endian data. Rows are stored bottom to top.
+        write!(writer, "Pf\n{} {}\n-1.0\n", self.width, self.height)?;
+        let row_length = self.width * self.samples.count();
+        for row in self.depth_attachment.chunks_exact(row_length).rev() {
+            for depth in row.iter().step_by(self.samples.count()) {
+                writer.write_all(&depth.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "window")]
     pub fn update_window(&self, window: &mut Window) {
+        assert_eq!(self.samples, SampleCount::X1);
         window
-            .update_with_buffer(&self.color_attachment, self.width, self.height)
+            .update_with_buffer(self.color_attachment(), self.width, self.height)
             .unwrap();
     }
 
@@ -74,7 +451,290 @@ impl Framebuffer {
         self.height
     }
 
+    pub fn sample_count(&self) -> SampleCount {
+        self.samples
+    }
+
     pub fn contains(&self, coords: (usize, usize)) -> bool {
         coords.0 < self.width && coords.1 < self.height
     }
+
+    #[inline]
+    fn index(&self, coords: (usize, usize), sample: usize) -> usize {
+        map_coords_to_index(coords, self.width) * self.samples.count() + sample
+    }
+
+    /// Average color of the samples of a pixel.
+    fn pixel_color(&self, pixel: usize) -> Color {
+        let count = self.samples.count();
+        let sum = (pixel * count..(pixel + 1) * count).fold(Color::zeros(), |sum, index| {
+            sum + self.color_attachment.get(index, self.color_space)
+        });
+        sum / count as f32
+    }
+
+    pub fn as_band_mut(&mut self) -> FramebufferBand<'_> {
+        FramebufferBand {
+            color_attachment: self.color_attachment.as_mut(),
+            color_space: self.color_space,
+            depth_attachment: &mut self.depth_attachment,
+            stencil_attachment: &mut self.stencil_attachment,
+            width: self.width,
+            y: 0,
+            height: self.height,
+            samples: self.samples,
+        }
+    }
+
+    /// Splits the framebuffer into disjoint bands of `band_height` rows, which can be rendered to
+    /// in parallel.
+    pub fn split_bands_mut(&mut self, band_height: usize) -> Vec<FramebufferBand<'_>> {
+        let chunk_size = band_height * self.width * self.samples.count();
+        self.color_attachment
+            .as_mut()
+            .split(chunk_size)
+            .into_iter()
+            .zip(self.depth_attachment.chunks_mut(chunk_size))
+            .zip(self.stencil_attachment.chunks_mut(chunk_size))
+            .enumerate()
+            .map(|(i, ((color, depth), stencil))| FramebufferBand {
+                height: depth.len() / (self.width * self.samples.count()),
+                color_attachment: color,
+                color_space: self.color_space,
+                depth_attachment: depth,
+                stencil_attachment: stencil,
+                width: self.width,
+                y: i * band_height,
+                samples: self.samples,
+            })
+            .collect()
+    }
+}
+
+/// Mutable view of a horizontal band of a [`Framebuffer`]. Coordinates are relative to the whole
+/// framebuffer.
+pub struct FramebufferBand<'a> {
+    color_attachment: ColorAttachmentMut<'a>,
+    color_space: ColorSpace,
+    depth_attachment: &'a mut [f32],
+    stencil_attachment: &'a mut [u8],
+    width: usize,
+    y: usize,
+    height: usize,
+    samples: SampleCount,
+}
+
+impl FramebufferBand<'_> {
+    pub fn test_and_set_depth(
+        &mut self,
+        coords: (usize, usize),
+        sample: usize,
+        depth: f32,
+        compare_op: CompareOp,
+        write_enable: bool,
+    ) -> bool {
+        let target = &mut self.depth_attachment[self.index(coords, sample)];
+        if !compare_op.test(depth, *target) {
+            return false;
+        }
+        if write_enable {
+            *target = depth;
+        }
+        true
+    }
+
+    pub fn get_depth(&self, coords: (usize, usize), sample: usize) -> f32 {
+        self.depth_attachment[self.index(coords, sample)]
+    }
+
+    pub fn get_stencil(&self, coords: (usize, usize), sample: usize) -> u8 {
+        self.stencil_attachment[self.index(coords, sample)]
+    }
+
+    pub fn set_stencil(&mut self, coords: (usize, usize), sample: usize, stencil: u8) {
+        self.stencil_attachment[self.index(coords, sample)] = stencil;
+    }
+
+    pub fn set_color(&mut self, coords: (usize, usize), sample: usize, color: Color) {
+        let index = self.index(coords, sample);
+        self.color_attachment.set(index, color, self.color_space);
+    }
+
+    pub fn get_color(&self, coords: (usize, usize), sample: usize) -> Color {
+        self.color_attachment
+            .get(self.index(coords, sample), self.color_space)
+    }
+
+    pub fn sample_count(&self) -> SampleCount {
+        self.samples
+    }
+
+    pub fn bounds(&self) -> Rect {
+        Rect::new(0, self.y, self.width, self.height)
+    }
+
+    #[inline]
+    fn index(&self, coords: (usize, usize), sample: usize) -> usize {
+        map_coords_to_index((coords.0, coords.1 - self.y), self.width) * self.samples.count()
+            + sample
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use super::*;
+    use crate::{
+        color::{to_raw_color, BLACK, WHITE},
+        tonemap::ToneMapOperator,
+    };
+
+    #[test]
+    fn resolve_averages_samples() {
+        let mut framebuffer = Framebuffer::new_multisampled(1, 1, SampleCount::X2);
+        let mut resolved = Framebuffer::new(1, 1);
+        framebuffer.clear(BLACK, 1.0);
+        framebuffer.as_band_mut().set_color((0, 0), 1, WHITE);
+
+        framebuffer.resolve(&mut resolved);
+
+        assert_eq!(to_raw_color(resolved.get_color((0, 0))), 0xFF7F7F7F);
+    }
+
+    #[test]
+    fn srgb_samples_resolve_in_linear_space() {
+        let mut framebuffer =
+            Framebuffer::new_multisampled(1, 1, SampleCount::X2).with_color_space(ColorSpace::Srgb);
+        let mut resolved = Framebuffer::new(1, 1).with_color_space(ColorSpace::Srgb);
+        framebuffer.clear(BLACK, 1.0);
+        framebuffer.as_band_mut().set_color((0, 0), 1, WHITE);
+
+        framebuffer.resolve(&mut resolved);
+
+        assert_eq!(resolved.color_attachment(), [0xFFBCBCBC]);
+        assert_eq!(resolved.to_image().color_space(), ColorSpace::Srgb);
+    }
+
+    #[test]
+    fn float_formats_keep_colors_above_one() {
+        let bright = Color::new(4.0, 0.5, 1024.0, 1.0);
+        for format in [ColorFormat::Rgba16F, ColorFormat::Rgba32F] {
+            let mut framebuffer = Framebuffer::new(2, 1).with_color_format(format);
+            framebuffer.clear(BLACK, 1.0);
+
+            framebuffer.set_color((1, 0), bright);
+
+            assert_eq!(framebuffer.color_format(), format);
+            assert_eq!(framebuffer.get_color((1, 0)), bright);
+            let image = framebuffer.to_image();
+            assert!(image.is_hdr());
+            assert_eq!(image.get_color((1, 0)), bright);
+        }
+
+        let mut framebuffer = Framebuffer::new(1, 1);
+        framebuffer.set_color((0, 0), bright);
+        assert_eq!(framebuffer.color_attachment(), [0xFFFF7FFF]);
+    }
+
+    #[test]
+    fn tone_map_resolves_to_eight_bits() {
+        let mut framebuffer = Framebuffer::new_multisampled(1, 1, SampleCount::X2)
+            .with_color_format(ColorFormat::Rgba16F);
+        let mut display = Framebuffer::new(1, 1).with_color_space(ColorSpace::Srgb);
+        framebuffer.clear(Color::new(1.0, 6.0, 0.0, 1.0), 1.0);
+        framebuffer
+            .as_band_mut()
+            .set_color((0, 0), 1, Color::new(5.0, 0.0, 0.0, 1.0));
+
+        framebuffer.tone_map(
+            &mut display,
+            ToneMapping::new(ToneMapOperator::Reinhard).with_exposure(-1.0),
+        );
+
+        // The samples average to (3, 3, 0), halved by the exposure and mapped to 0.6.
+        let expected = ColorSpace::Srgb.encode(Color::new(0.6, 0.6, 0.0, 1.0));
+        assert_eq!(display.color_attachment(), [expected]);
+    }
+
+    #[test]
+    fn downsample_filters() {
+        let mut supersampled = Framebuffer::new(4, 2);
+        let mut destination = Framebuffer::new(2, 1);
+        supersampled.clear(BLACK, 1.0);
+        for y in 0..2 {
+            supersampled.set_color((1, y), WHITE);
+            supersampled.set_color((2, y), WHITE);
+        }
+
+        supersampled.downsample(&mut destination, DownsampleFilter::Box);
+        assert_eq!(to_raw_color(destination.get_color((0, 0))), 0xFF7F7F7F);
+
+        // The tent reaches into the neighbouring block, but weighs the inner column more.
+        supersampled.downsample(&mut destination, DownsampleFilter::Tent);
+        let tent = destination.get_color((0, 0));
+        assert!(tent.x > 0.5 && tent.x < 1.0);
+        assert_eq!(destination.get_color((1, 0)), tent);
+    }
+
+    #[test]
+    fn downsample_needs_an_integer_factor() {
+        let source = Framebuffer::new(4, 2);
+        let downsample = |width, height| {
+            let mut destination = Framebuffer::new(width, height);
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                source.downsample(&mut destination, DownsampleFilter::Box)
+            }))
+            .is_ok()
+        };
+
+        assert!(downsample(2, 1));
+        assert!(downsample(4, 2));
+        assert!(!downsample(0, 0));
+        assert!(!downsample(8, 4));
+        assert!(!downsample(3, 2));
+        assert!(!downsample(2, 2));
+    }
+
+    #[test]
+    fn read_back_without_a_window() {
+        let mut framebuffer = Framebuffer::new(2, 1);
+        framebuffer.clear(BLACK, 0.5);
+        framebuffer.set_color((1, 0), WHITE);
+
+        assert_eq!(framebuffer.color_attachment(), [0xFF000000, 0xFFFFFFFF]);
+        assert_eq!(framebuffer.depth_attachment(), [0.5, 0.5]);
+        let image = framebuffer.to_image();
+        assert_eq!((image.width(), image.height()), (2, 1));
+        assert_eq!(image.get_color((1, 0)), WHITE);
+    }
+
+    #[test]
+    fn depth_is_normalized_to_grayscale() {
+        let mut framebuffer = Framebuffer::new(3, 1);
+        framebuffer.clear(BLACK, f32::INFINITY);
+        framebuffer.test_and_set_depth((0, 0), 0.25, CompareOp::Always, true);
+        framebuffer.test_and_set_depth((1, 0), 0.75, CompareOp::Always, true);
+
+        let image = framebuffer.depth_image();
+
+        assert_eq!(image.get_color((0, 0)), BLACK);
+        assert_eq!(image.get_color((1, 0)), WHITE);
+        assert_eq!(image.get_color((2, 0)), WHITE);
+    }
+
+    #[test]
+    fn depth_pfm_is_stored_bottom_up() {
+        let mut framebuffer = Framebuffer::new(1, 2);
+        framebuffer.clear(BLACK, 1.0);
+        framebuffer.test_and_set_depth((0, 0), 0.5, CompareOp::Always, true);
+        let mut pfm = Vec::new();
+
+        framebuffer.write_depth_pfm(&mut pfm).unwrap();
+
+        let header = b"Pf\n1 2\n-1.0\n";
+        assert_eq!(&pfm[..header.len()], header);
+        assert_eq!(&pfm[header.len()..header.len() + 4], 1.0f32.to_le_bytes());
+        assert_eq!(&pfm[header.len() + 4..], 0.5f32.to_le_bytes());
+    }
 }
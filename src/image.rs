@@ -1,29 +1,71 @@
-use std::path::PathBuf;
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
 
-use image::Rgba;
+use image::{codecs::hdr::HdrEncoder, DynamicImage, Rgb, Rgba, Rgba32FImage, RgbaImage};
 
-use crate::color::{from_raw_color, Color};
+use crate::{
+    color::{Color, ColorSpace},
+    mipmap::{generate_mip_chain, MipmapFilter},
+};
 
 pub type Coords2D = (usize, usize);
 
+/// Pixels of an image, either 8-bit or floating-point.
+enum Pixels {
+    /// Packed as `0xAARRGGBB` in the image's color space.
+    Rgba8(Vec<u32>),
+    /// Linear and unbounded, for high dynamic range images.
+    Rgba32F(Vec<Color>),
+}
+
 pub struct Image {
-    buffer: Vec<u32>,
+    pixels: Pixels,
     width: usize,
     height: usize,
+    color_space: ColorSpace,
+    /// Levels after the base one, each half the size of the previous.
+    mip_levels: Vec<Image>,
 }
 
 impl Image {
     pub fn from_buffer(buffer: Vec<u32>, width: usize, height: usize) -> Self {
         assert_eq!(width * height, buffer.len());
         Self {
-            buffer,
+            pixels: Pixels::Rgba8(buffer),
+            width,
+            height,
+            color_space: ColorSpace::Linear,
+            mip_levels: Vec::new(),
+        }
+    }
+
+    /// Creates a high dynamic range image from linear colors.
+    pub fn from_hdr_buffer(buffer: Vec<Color>, width: usize, height: usize) -> Self {
+        assert_eq!(width * height, buffer.len());
+        Self {
+            pixels: Pixels::Rgba32F(buffer),
             width,
             height,
+            color_space: ColorSpace::Linear,
+            mip_levels: Vec::new(),
         }
     }
 
+    /// Loads an image file, assuming it is sRGB-encoded like most color images. Use
+    /// [`Image::with_color_space`] to load data such as normal maps linearly. Floating-point
+    /// formats such as Radiance `.hdr` and OpenEXR are loaded as linear HDR images.
     pub fn from_file(path: PathBuf) -> image::ImageResult<Self> {
-        let image = image::open(path)?.to_rgba8();
+        let image = image::open(path)?;
+        if let DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) = image {
+            let image = image.into_rgba32f();
+            let (width, height) = (image.width() as usize, image.height() as usize);
+            let buffer = image.pixels().map(|&Rgba(c)| Color::from(c)).collect();
+            return Ok(Self::from_hdr_buffer(buffer, width, height));
+        }
+        let image = image.to_rgba8();
         let width = image.width() as usize;
         let height = image.height() as usize;
         Ok(Self::from_buffer(
@@ -34,12 +76,118 @@ impl Image {
                 .collect(),
             width,
             height,
-        ))
+        )
+        .with_color_space(ColorSpace::Srgb))
+    }
+
+    /// Sets how the stored bytes are decoded when sampled, without converting them. Mip levels
+    /// are reinterpreted too, so generate them after choosing the color space.
+    pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        for level in &mut self.mip_levels {
+            level.color_space = color_space;
+        }
+        self
+    }
+
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
+    /// Writes the image to a file, in the format given by the extension of `path`. HDR images
+    /// need a floating-point format such as `.hdr` or `.exr`.
+    pub fn save(&self, path: impl AsRef<Path>) -> image::ImageResult<()> {
+        let (width, height) = (self.width as u32, self.height as u32);
+        match &self.pixels {
+            Pixels::Rgba8(buffer) => {
+                let pixels = buffer
+                    .iter()
+                    .flat_map(|&raw| [raw >> 16, raw >> 8, raw, raw >> 24].map(|c| c as u8))
+                    .collect();
+                RgbaImage::from_raw(width, height, pixels)
+                    .unwrap()
+                    .save(path)
+            }
+            Pixels::Rgba32F(buffer)
+                if path
+                    .as_ref()
+                    .extension()
+                    .is_some_and(|extension| extension.eq_ignore_ascii_case("hdr")) =>
+            {
+                // The Radiance format has no alpha channel.
+                let pixels = buffer
+                    .iter()
+                    .map(|c| Rgb([c.x, c.y, c.z]))
+                    .collect::<Vec<_>>();
+                let writer = BufWriter::new(File::create(path)?);
+                HdrEncoder::new(writer).encode(&pixels, self.width, self.height)
+            }
+            Pixels::Rgba32F(buffer) => {
+                let pixels = buffer.iter().flat_map(|c| c.iter().copied()).collect();
+                Rgba32FImage::from_raw(width, height, pixels)
+                    .unwrap()
+                    .save(path)
+            }
+        }
+    }
+
+    /// Whether the image stores floating-point colors that may exceed 1.0.
+    pub fn is_hdr(&self) -> bool {
+        matches!(self.pixels, Pixels::Rgba32F(_))
+    }
+
+    /// Generates the full mip chain of the image, replacing any existing one.
+    pub fn generate_mipmaps(&mut self, filter: MipmapFilter) {
+        self.mip_levels = generate_mip_chain(self, filter);
+    }
+
+    pub fn with_mipmaps(mut self, filter: MipmapFilter) -> Self {
+        self.generate_mipmaps(filter);
+        self
     }
 
+    /// Uses precomputed mip levels, each half the size of the previous one.
+    pub fn with_mip_levels(mut self, levels: Vec<Image>) -> Self {
+        let mut size = (self.width, self.height);
+        for level in &levels {
+            size = ((size.0 / 2).max(1), (size.1 / 2).max(1));
+            assert_eq!((level.width, level.height), size);
+            assert_eq!(level.color_space, self.color_space);
+        }
+        self.mip_levels = levels;
+        self
+    }
+
+    /// Number of mip levels, including the base level.
+    pub fn mip_level_count(&self) -> usize {
+        self.mip_levels.len() + 1
+    }
+
+    /// Returns the given mip level, where level 0 is the image itself.
+    pub fn mip_level(&self, level: usize) -> &Image {
+        match level {
+            0 => self,
+            _ => &self.mip_levels[level - 1],
+        }
+    }
+
+    /// Returns the linear color of a pixel.
     pub fn get_color(&self, coords: Coords2D) -> Color {
         assert!(self.contains(coords));
-        from_raw_color(self.buffer[map_coords_to_index(coords, self.width)])
+        let index = map_coords_to_index(coords, self.width);
+        match &self.pixels {
+            Pixels::Rgba8(buffer) => self.color_space.decode(buffer[index]),
+            Pixels::Rgba32F(buffer) => buffer[index],
+        }
+    }
+
+    /// Raw pixels, packed as `0xAARRGGBB` in the image's color space, in row-major order.
+    /// Panics for HDR images.
+    pub fn buffer(&self) -> &[u32] {
+        match &self.pixels {
+            Pixels::Rgba8(buffer) => buffer,
+            Pixels::Rgba32F(_) => panic!("HDR images have no 8-bit buffer"),
+        }
     }
 
     pub fn width(&self) -> usize {
@@ -79,4 +227,37 @@ mod tests {
         assert_eq!(image.get_color((0, 1)), GREEN);
         assert_eq!(image.get_color((1, 1)), BLUE);
     }
+
+    #[test]
+    fn save_and_reload() {
+        let image = Image::from_buffer(vec![0xFF102030, 0x80FFFFFF], 2, 1);
+        let path = std::env::temp_dir().join("rasterization_save_and_reload.png");
+
+        image.save(&path).unwrap();
+        let reloaded = Image::from_file(path.clone()).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(reloaded.buffer(), image.buffer());
+    }
+
+    #[test]
+    fn save_and_reload_radiance_hdr() {
+        let colors = vec![
+            Color::new(4.0, 0.5, 0.0, 1.0),
+            Color::new(0.25, 1.0, 100.0, 1.0),
+        ];
+        let image = Image::from_hdr_buffer(colors.clone(), 2, 1);
+        let path = std::env::temp_dir().join("rasterization_save_and_reload.hdr");
+
+        image.save(&path).unwrap();
+        let reloaded = Image::from_file(path.clone()).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert!(reloaded.is_hdr());
+        for (x, expected) in colors.into_iter().enumerate() {
+            // RGBE shares one exponent between the channels, keeping 8 bits of mantissa.
+            let error = (reloaded.get_color((x, 0)) - expected).abs().max();
+            assert!(error <= expected.max() / 128.0, "{x}: {error}");
+        }
+    }
 }
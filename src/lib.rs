@@ -2,10 +2,21 @@ pub mod clipping;
 pub mod color;
 pub mod framebuffer;
 pub mod image;
+pub mod mipmap;
 pub mod model;
+pub mod multisample;
+pub mod obj;
 pub mod pipeline;
+pub mod ply;
 pub mod rasterization;
 pub mod sampler;
+pub mod scene;
+pub mod shader;
+pub mod state;
+pub mod stl;
+pub mod tonemap;
+pub mod topology;
 pub mod triangulation;
+pub mod varyings;
 pub mod vertex;
 pub mod viewport;
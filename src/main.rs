@@ -3,12 +3,16 @@ use std::f32::consts::PI;
 use minifb::{Key, KeyRepeat, Window, WindowOptions};
 use nalgebra_glm::{vec2, vec3};
 use rasterization_in_a_weekend::{
-    color::{BLACK, BLUE, GREEN, RED, WHITE},
+    color::{ColorSpace, BLACK, BLUE, GREEN, RED, WHITE},
     framebuffer::Framebuffer,
     image::Image,
-    model::unit_cube,
+    mipmap::MipmapFilter,
+    model::unit_cube_indexed,
+    multisample::SampleCount,
     pipeline::RasterizationPipeline,
-    sampler::{AddressMode, Filter, Sampler},
+    sampler::{AddressMode, Filter, MipmapMode, Sampler},
+    shader::{TextureShader, TransformShader},
+    topology::PrimitiveTopology,
     vertex::Vertex,
     viewport::Viewport,
 };
@@ -18,7 +22,11 @@ const WINDOW_WIDTH: usize = 640;
 const WINDOW_HEIGHT: usize = 360;
 
 fn main() {
-    let mut framebuffer = Framebuffer::new(WINDOW_WIDTH, WINDOW_HEIGHT);
+    let mut framebuffer =
+        Framebuffer::new_multisampled(WINDOW_WIDTH, WINDOW_HEIGHT, SampleCount::X4)
+            .with_color_space(ColorSpace::Srgb);
+    let mut resolved =
+        Framebuffer::new(WINDOW_WIDTH, WINDOW_HEIGHT).with_color_space(ColorSpace::Srgb);
     let mut window = Window::new(
         WINDOW_TITLE,
         WINDOW_WIDTH,
@@ -28,13 +36,16 @@ fn main() {
     .unwrap();
     window.set_target_fps(60);
 
-    let image = Image::from_file("textures/historical.jpg".into()).unwrap();
+    let image = Image::from_file("textures/historical.jpg".into())
+        .unwrap()
+        .with_mipmaps(MipmapFilter::Kaiser);
     let sampler = Sampler::new(
         AddressMode::Clamp,
         AddressMode::Clamp,
         Filter::Anisotropic(4),
         Filter::Linear,
-    );
+    )
+    .with_mipmap_mode(MipmapMode::Linear);
     let viewport = Viewport::full(WINDOW_WIDTH as f32, WINDOW_HEIGHT as f32);
     let projection = nalgebra_glm::perspective_fov_rh_zo(
         PI / 3.0,
@@ -53,7 +64,8 @@ fn main() {
         &nalgebra_glm::translate(&nalgebra_glm::identity(), &vec3(0.0, 0.0, 10.0)),
         &vec3(2.0, 2.0, 2.0),
     );
-    let pipeline = RasterizationPipeline::new(viewport);
+    let mut pipeline = RasterizationPipeline::new(viewport);
+    pipeline.set_thread_count(std::thread::available_parallelism().map_or(1, |n| n.get()));
     let mut colors = std::iter::repeat([RED, GREEN, BLUE, WHITE]).flatten();
     let mut uv = std::iter::repeat([
         vec2(0.0, 0.0),
@@ -62,29 +74,32 @@ fn main() {
         vec2(1.0, 0.0),
     ])
     .flatten();
-    let vertices = unit_cube(|_, c| {
+    let (lower_cube, cube_indices) = unit_cube_indexed(|_, c| {
         Vertex::new(
             c - vec3(0.0, 2.0, 0.0),
             colors.next().unwrap(),
             uv.next().unwrap(),
         )
-    })
-    .into_iter()
-    .chain(unit_cube(|_, c| {
+    });
+    let (upper_cube, _) = unit_cube_indexed(|_, c| {
         Vertex::new(
             c + vec3(0.0, 2.0, 0.0),
             colors.next().unwrap(),
             uv.next().unwrap(),
         )
-    }))
-    .collect::<Vec<_>>();
+    });
+    let vertices = lower_cube.into_iter().chain(upper_cube).collect::<Vec<_>>();
+    let indices = cube_indices
+        .into_iter()
+        .chain(cube_indices.map(|i| i + lower_cube.len() as u16))
+        .collect::<Vec<_>>();
 
     let amplitude = 1.0;
     let speed = PI / 60.0;
     let rotation = PI / 150.0;
     let mut frame = 0;
     while window.is_open() && !window.is_key_pressed(Key::Escape, KeyRepeat::No) {
-        framebuffer.clear(BLACK, std::f32::INFINITY);
+        framebuffer.clear(BLACK, f32::INFINITY);
         let f32_frame = frame as f32;
         let angle = f32_frame * rotation;
         let z_delta = amplitude * f32::cos(f32_frame * speed);
@@ -92,9 +107,17 @@ fn main() {
             &nalgebra_glm::translate(&default_world, &vec3(0.0, 0.0, z_delta)),
             angle,
         );
-        let transform = proj_view * world;
-        pipeline.draw_triangles(&mut framebuffer, &transform, (&image, &sampler), &vertices);
-        framebuffer.update_window(&mut window);
+        let vertex_shader = TransformShader::new(proj_view * world);
+        let fragment_shader = TextureShader::new(&image, &sampler);
+        pipeline.draw_indexed(
+            &mut framebuffer,
+            PrimitiveTopology::TriangleList,
+            (&vertex_shader, &fragment_shader),
+            &vertices,
+            &indices,
+        );
+        framebuffer.resolve(&mut resolved);
+        resolved.update_window(&mut window);
         frame += 1;
     }
 }
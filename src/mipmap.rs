@@ -0,0 +1,226 @@
+use std::f32::consts::PI;
+
+use crate::{color::Color, image::Image};
+
+/// Reconstruction filter used to downsample one mip level into the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MipmapFilter {
+    /// Averages the texels covered by each texel of the next level.
+    #[default]
+    Box,
+    /// Kaiser-windowed sinc, sharper than a box with little ringing.
+    Kaiser,
+    /// Three-lobed Lanczos, the sharpest but prone to ringing around hard edges.
+    Lanczos,
+}
+
+impl MipmapFilter {
+    /// Half-width of the kernel, in texels of the destination level.
+    fn radius(self) -> f32 {
+        match self {
+            MipmapFilter::Box => 0.5,
+            MipmapFilter::Kaiser | MipmapFilter::Lanczos => 3.0,
+        }
+    }
+
+    fn weight(self, t: f32) -> f32 {
+        const KAISER_BETA: f32 = 4.0;
+        let radius = self.radius();
+        if t.abs() > radius {
+            return 0.0;
+        }
+        match self {
+            MipmapFilter::Box => 1.0,
+            MipmapFilter::Kaiser => {
+                let x = t / radius;
+                sinc(t) * bessel_i0(KAISER_BETA * (1.0 - x * x).sqrt()) / bessel_i0(KAISER_BETA)
+            }
+            MipmapFilter::Lanczos => sinc(t) * sinc(t / radius),
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Zeroth order modified Bessel function of the first kind, from its power series.
+fn bessel_i0(x: f32) -> f32 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let mut k = 1.0;
+    while term > sum * 1e-7 {
+        term *= (x / (2.0 * k)) * (x / (2.0 * k));
+        sum += term;
+        k += 1.0;
+    }
+    sum
+}
+
+/// Halves `image` down to 1x1, returning every level after the base one.
+pub fn generate_mip_chain(image: &Image, filter: MipmapFilter) -> Vec<Image> {
+    let mut levels: Vec<Image> = Vec::new();
+    let (mut width, mut height) = (image.width(), image.height());
+    while width > 1 || height > 1 {
+        width = (width / 2).max(1);
+        height = (height / 2).max(1);
+        let source = levels.last().unwrap_or(image);
+        levels.push(downsample(source, width, height, filter));
+    }
+    levels
+}
+
+/// Resamples `image` to a smaller size with a separable filter, in linear space.
+fn downsample(image: &Image, width: usize, height: usize, filter: MipmapFilter) -> Image {
+    let source = (0..image.height())
+        .flat_map(|y| (0..image.width()).map(move |x| (x, y)))
+        .map(|coords| image.get_color(coords))
+        .collect::<Vec<_>>();
+    let horizontal = resample_rows(&source, image.width(), image.height(), width, filter);
+    let transposed = transpose(&horizontal, width, image.height());
+    let vertical = resample_rows(&transposed, image.height(), width, height, filter);
+    let colors = transpose(&vertical, height, width);
+    if image.is_hdr() {
+        return Image::from_hdr_buffer(colors, width, height);
+    }
+    let buffer = colors
+        .into_iter()
+        .map(|color| image.color_space().encode(color))
+        .collect();
+    Image::from_buffer(buffer, width, height).with_color_space(image.color_space())
+}
+
+/// Resamples each row of `pixels` from `width` to `new_width` texels.
+fn resample_rows(
+    pixels: &[Color],
+    width: usize,
+    height: usize,
+    new_width: usize,
+    filter: MipmapFilter,
+) -> Vec<Color> {
+    let scale = width as f32 / new_width as f32;
+    let taps = (0..new_width)
+        .map(|x| {
+            let center = (x as f32 + 0.5) * scale;
+            let first = (center - filter.radius() * scale).floor() as isize;
+            let last = (center + filter.radius() * scale).ceil() as isize;
+            let taps = (first..=last)
+                .map(|i| {
+                    let weight = filter.weight((i as f32 + 0.5 - center) / scale);
+                    (i.clamp(0, width as isize - 1) as usize, weight)
+                })
+                .filter(|&(_, weight)| weight != 0.0)
+                .collect::<Vec<_>>();
+            let total = taps.iter().map(|&(_, weight)| weight).sum::<f32>();
+            taps.into_iter()
+                .map(|(i, weight)| (i, weight / total))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    pixels
+        .chunks_exact(width)
+        .take(height)
+        .flat_map(|row| {
+            taps.iter()
+                .map(|taps| taps.iter().map(|&(i, weight)| weight * row[i]).sum())
+        })
+        .collect()
+}
+
+fn transpose(pixels: &[Color], width: usize, height: usize) -> Vec<Color> {
+    (0..width)
+        .flat_map(|x| (0..height).map(move |y| pixels[y * width + x]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{from_raw_color, to_raw_color, ColorSpace, BLACK, WHITE};
+
+    fn checkerboard(size: usize) -> Image {
+        let buffer = (0..size * size)
+            .map(|i| {
+                let color = if (i % size + i / size) % 2 == 0 {
+                    BLACK
+                } else {
+                    WHITE
+                };
+                to_raw_color(color)
+            })
+            .collect();
+        Image::from_buffer(buffer, size, size)
+    }
+
+    #[test]
+    fn chain_halves_down_to_one_texel() {
+        let image = Image::from_buffer(vec![0; 12 * 5], 12, 5);
+
+        let sizes = generate_mip_chain(&image, MipmapFilter::Box)
+            .iter()
+            .map(|level| (level.width(), level.height()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(sizes, [(6, 2), (3, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn filters_average_a_checkerboard_to_gray() {
+        for filter in [
+            MipmapFilter::Box,
+            MipmapFilter::Kaiser,
+            MipmapFilter::Lanczos,
+        ] {
+            let levels = generate_mip_chain(&checkerboard(16), filter);
+
+            // Texels near the border see the clamped edge instead of the repeating pattern.
+            for y in 2..6 {
+                for x in 2..6 {
+                    let color = levels[0].get_color((x, y));
+                    assert!((color.x - 0.5).abs() < 0.01, "{filter:?}: {color:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn filters_preserve_constant_colors() {
+        let color = 0xFF4080C0;
+        let image = Image::from_buffer(vec![color; 10 * 6], 10, 6);
+
+        for filter in [
+            MipmapFilter::Box,
+            MipmapFilter::Kaiser,
+            MipmapFilter::Lanczos,
+        ] {
+            for level in generate_mip_chain(&image, filter) {
+                assert_eq!(level.get_color((0, 0)), from_raw_color(color), "{filter:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn box_filter_averages_texel_quads() {
+        let image = Image::from_buffer(vec![0xFF000000, 0xFF0000FF, 0xFF00FF00, 0xFFFF0000], 2, 2);
+
+        let levels = generate_mip_chain(&image, MipmapFilter::Box);
+
+        let color = levels[0].get_color((0, 0));
+        assert!((color - Color::new(0.25, 0.25, 0.25, 1.0)).abs().max() < 1.0 / 255.0);
+    }
+
+    #[test]
+    fn srgb_images_are_filtered_in_linear_space() {
+        let image = Image::from_buffer(vec![0xFF000000, 0xFFFFFFFF], 2, 1)
+            .with_color_space(ColorSpace::Srgb);
+
+        let levels = generate_mip_chain(&image, MipmapFilter::Box);
+
+        assert_eq!(levels[0].color_space(), ColorSpace::Srgb);
+        assert_eq!(levels[0].buffer()[0], 0xFFBCBCBC);
+    }
+}
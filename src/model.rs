@@ -1,8 +1,8 @@
 use std::f32::consts::PI;
 
-use nalgebra_glm::{vec2, Vec2, Vec3};
+use nalgebra_glm::{vec2, Mat3, Vec2, Vec3};
 
-pub fn unit_triangle<V>(mut f: impl FnMut(Vec2) -> V) -> [V; 3] {
+pub fn unit_triangle<V>(f: impl FnMut(Vec2) -> V) -> [V; 3] {
     [vec2(0.0, -0.5), vec2(-0.5, 0.5), vec2(0.5, 0.5)].map(f)
 }
 
@@ -21,7 +21,20 @@ pub fn unit_quad<V: Clone>(mut f: impl FnMut(Vec2) -> V) -> [V; 6] {
     ]
 }
 
+pub const UNIT_QUAD_INDICES: [u16; 6] = [0, 1, 3, 1, 2, 3];
+
+pub fn unit_quad_indexed<V>(f: impl FnMut(Vec2) -> V) -> ([V; 4], [u16; 6]) {
+    let corners = [
+        vec2(-0.5, -0.5),
+        vec2(-0.5, 0.5),
+        vec2(0.5, 0.5),
+        vec2(0.5, -0.5),
+    ];
+    (corners.map(f), UNIT_QUAD_INDICES)
+}
+
 #[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CubeSide {
     Top,
     Left,
@@ -31,42 +44,50 @@ pub enum CubeSide {
     Backward,
 }
 
-pub fn unit_cube<V: Clone>(mut f: impl FnMut(CubeSide, Vec3) -> V) -> [V; 36] {
+fn cube_sides() -> [(CubeSide, Mat3); 6] {
     use nalgebra_glm::identity;
 
-    let transform = nalgebra_glm::rotate_x(&identity(), PI / 2.0).fixed_resize::<3, 3>(0.0);
-    let top = unit_quad(|coords| f(CubeSide::Top, transform * coords.push(0.5)));
-
-    let transform = nalgebra_glm::rotate_y(&identity(), -PI / 2.0).fixed_resize::<3, 3>(0.0);
-    let left = unit_quad(|coords| f(CubeSide::Left, transform * coords.push(0.5)));
-
-    let transform = nalgebra_glm::rotate_x(&identity(), -PI / 2.0).fixed_resize::<3, 3>(0.0);
-    let bottom = unit_quad(|coords| f(CubeSide::Bottom, transform * coords.push(0.5)));
-
-    let transform = nalgebra_glm::rotate_y(&identity(), PI / 2.0).fixed_resize::<3, 3>(0.0);
-    let right = unit_quad(|coords| f(CubeSide::Right, transform * coords.push(0.5)));
-
-    let transform = nalgebra_glm::rotate_x(&identity(), PI).fixed_resize::<3, 3>(0.0);
-    let forward = unit_quad(|coords| f(CubeSide::Forward, transform * coords.push(0.5)));
-
-    let transform = identity();
-    let backward = unit_quad(|coords| f(CubeSide::Backward, transform * coords.push(0.5)));
+    [
+        (CubeSide::Top, nalgebra_glm::rotate_x(&identity(), PI / 2.0)),
+        (
+            CubeSide::Left,
+            nalgebra_glm::rotate_y(&identity(), -PI / 2.0),
+        ),
+        (
+            CubeSide::Bottom,
+            nalgebra_glm::rotate_x(&identity(), -PI / 2.0),
+        ),
+        (
+            CubeSide::Right,
+            nalgebra_glm::rotate_y(&identity(), PI / 2.0),
+        ),
+        (CubeSide::Forward, nalgebra_glm::rotate_x(&identity(), PI)),
+        (CubeSide::Backward, identity()),
+    ]
+    .map(|(side, transform)| (side, transform.fixed_resize::<3, 3>(0.0)))
+}
 
-    let mut iter = top
+pub fn unit_cube<V: Clone>(mut f: impl FnMut(CubeSide, Vec3) -> V) -> [V; 36] {
+    let mut iter = cube_sides()
         .into_iter()
-        .chain(left)
-        .chain(bottom)
-        .chain(right)
-        .chain(forward)
-        .chain(backward);
+        .flat_map(|(side, transform)| unit_quad(|coords| f(side, transform * coords.push(0.5))));
     std::array::from_fn(|_| iter.next().unwrap())
 }
 
+pub fn unit_cube_indexed<V>(mut f: impl FnMut(CubeSide, Vec3) -> V) -> ([V; 24], [u16; 36]) {
+    let mut iter = cube_sides().into_iter().flat_map(|(side, transform)| {
+        unit_quad_indexed(|coords| f(side, transform * coords.push(0.5))).0
+    });
+    let vertices = std::array::from_fn(|_| iter.next().unwrap());
+    let indices = std::array::from_fn(|i| 4 * (i / 6) as u16 + UNIT_QUAD_INDICES[i % 6]);
+    (vertices, indices)
+}
+
 #[cfg(test)]
 mod tests {
     use nalgebra_glm::Vec3;
 
-    use super::unit_cube;
+    use super::{unit_cube, unit_cube_indexed};
 
     #[test]
     fn unit_cube_primitives_are_counterclockwise() {
@@ -90,4 +111,11 @@ mod tests {
             vec![1.0; 12]
         );
     }
+
+    #[test]
+    fn unit_cube_indexed_matches_unit_cube() {
+        let (vertices, indices) = unit_cube_indexed(|_, i| i);
+
+        assert_eq!(indices.map(|i| vertices[i as usize]), unit_cube(|_, i| i));
+    }
 }
@@ -0,0 +1,75 @@
+use nalgebra_glm::{vec2, Vec2};
+
+/// Number of samples stored per pixel of a framebuffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SampleCount {
+    #[default]
+    X1,
+    X2,
+    X4,
+    X8,
+}
+
+impl SampleCount {
+    pub fn count(self) -> usize {
+        match self {
+            SampleCount::X1 => 1,
+            SampleCount::X2 => 2,
+            SampleCount::X4 => 4,
+            SampleCount::X8 => 8,
+        }
+    }
+
+    /// Coverage mask with a bit set for every sample.
+    pub fn mask(self) -> u32 {
+        (1 << self.count()) - 1
+    }
+
+    /// Standard sample positions, in sixteenths of a pixel relative to the pixel center.
+    pub fn positions(self) -> &'static [[i8; 2]] {
+        match self {
+            SampleCount::X1 => &[[0, 0]],
+            SampleCount::X2 => &[[4, 4], [-4, -4]],
+            SampleCount::X4 => &[[-2, -6], [6, -2], [-6, 2], [2, 6]],
+            SampleCount::X8 => &[
+                [1, -3],
+                [-1, 3],
+                [5, 1],
+                [-3, -5],
+                [-5, 5],
+                [-7, -1],
+                [3, 7],
+                [7, -7],
+            ],
+        }
+    }
+
+    /// Position of a sample in pixels, relative to the pixel center.
+    pub fn offset(self, sample: usize) -> Vec2 {
+        let [x, y] = self.positions()[sample];
+        vec2(x as f32, y as f32) / 16.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_are_distinct_and_inside_the_pixel() {
+        for samples in [
+            SampleCount::X1,
+            SampleCount::X2,
+            SampleCount::X4,
+            SampleCount::X8,
+        ] {
+            let positions = samples.positions();
+            assert_eq!(positions.len(), samples.count());
+            assert_eq!(samples.mask().count_ones() as usize, samples.count());
+            for (i, p) in positions.iter().enumerate() {
+                assert!(p.iter().all(|c| (-8..8).contains(c)));
+                assert!(!positions[..i].contains(p));
+            }
+        }
+    }
+}
@@ -0,0 +1,451 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Display},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use nalgebra_glm::{vec2, vec3, Vec2, Vec3};
+
+use crate::{
+    color::{Color, WHITE},
+    image::Image,
+    triangulation::fan_triangulate,
+    vertex::Vertex,
+};
+
+/// Meshes and materials loaded from a Wavefront OBJ file.
+pub struct ObjModel {
+    pub meshes: Vec<Mesh>,
+    pub materials: Vec<Material>,
+}
+
+/// Triangles of one group of an OBJ file that share a material.
+pub struct Mesh {
+    pub name: String,
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    /// Index into [`ObjModel::materials`].
+    pub material: Option<usize>,
+}
+
+pub struct Material {
+    pub name: String,
+    pub diffuse_color: Color,
+    pub diffuse_texture: Option<Image>,
+}
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    Texture {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+}
+
+impl Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io { path, source } => write!(f, "cannot read {}: {source}", path.display()),
+            ObjError::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{line}: {message}", path.display()),
+            ObjError::Texture { path, source } => {
+                write!(f, "cannot load texture {}: {source}", path.display())
+            }
+        }
+    }
+}
+
+impl Error for ObjError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ObjError::Io { source, .. } => Some(source),
+            ObjError::Parse { .. } => None,
+            ObjError::Texture { source, .. } => Some(source),
+        }
+    }
+}
+
+impl ObjModel {
+    /// Loads an OBJ file along with the material libraries and textures it references, which are
+    /// resolved relative to the file referencing them.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ObjError> {
+        let path = path.as_ref();
+        Self::parse(&read_to_string(path)?, path)
+    }
+
+    /// Parses the contents of an OBJ file. `path` is used to resolve material libraries and in
+    /// error messages.
+    pub fn parse(source: &str, path: &Path) -> Result<Self, ObjError> {
+        let mut parser = ObjParser {
+            path,
+            positions: Vec::new(),
+            uvs: Vec::new(),
+            normals: Vec::new(),
+            model: ObjModel {
+                meshes: Vec::new(),
+                materials: Vec::new(),
+            },
+            group: String::new(),
+            material: None,
+            vertex_indices: HashMap::new(),
+        };
+        for (i, line) in source.lines().enumerate() {
+            parser
+                .parse_line(line)
+                .map_err(|error| error.at(path, i + 1))?;
+        }
+        parser.model.meshes.retain(|mesh| !mesh.indices.is_empty());
+        Ok(parser.model)
+    }
+}
+
+/// Error raised while parsing a line, before it is tied to a location.
+enum LineError {
+    Parse(String),
+    Located(ObjError),
+}
+
+impl LineError {
+    fn at(self, path: &Path, line: usize) -> ObjError {
+        match self {
+            LineError::Parse(message) => ObjError::Parse {
+                path: path.to_owned(),
+                line,
+                message,
+            },
+            LineError::Located(error) => error,
+        }
+    }
+}
+
+impl From<ObjError> for LineError {
+    fn from(error: ObjError) -> Self {
+        LineError::Located(error)
+    }
+}
+
+struct ObjParser<'a> {
+    path: &'a Path,
+    positions: Vec<Vec3>,
+    uvs: Vec<Vec2>,
+    normals: Vec<Vec3>,
+    model: ObjModel,
+    group: String,
+    material: Option<usize>,
+    /// Vertices of the current mesh, by their position, texture coordinate and normal indices.
+    vertex_indices: HashMap<(usize, Option<usize>, Option<usize>), u32>,
+}
+
+impl ObjParser<'_> {
+    fn parse_line(&mut self, line: &str) -> Result<(), LineError> {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            return Ok(());
+        };
+        match keyword {
+            "v" => {
+                let v = parse_floats(&mut tokens, 3)?;
+                self.positions.push(vec3(v[0], v[1], v[2]));
+            }
+            "vt" => {
+                let uv = parse_floats(&mut tokens, 1)?;
+                // OBJ texture coordinates start at the bottom of the image.
+                self.uvs
+                    .push(vec2(uv[0], 1.0 - uv.get(1).copied().unwrap_or_default()));
+            }
+            "vn" => {
+                let n = parse_floats(&mut tokens, 3)?;
+                self.normals.push(vec3(n[0], n[1], n[2]));
+            }
+            "f" => self.parse_face(tokens)?,
+            "g" | "o" => {
+                self.group = tokens.collect::<Vec<_>>().join(" ");
+                self.vertex_indices.clear();
+            }
+            "usemtl" => {
+                let name = tokens.next().ok_or_else(|| missing("material name"))?;
+                let material = self
+                    .model
+                    .materials
+                    .iter()
+                    .position(|material| material.name == name)
+                    .ok_or_else(|| LineError::Parse(format!("unknown material `{name}`")))?;
+                self.material = Some(material);
+                self.vertex_indices.clear();
+            }
+            "mtllib" => {
+                for library in tokens {
+                    let path = resolve(self.path, library);
+                    let source = read_to_string(&path)?;
+                    parse_mtl(&source, &path, &mut self.model.materials)?;
+                }
+            }
+            // Smoothing groups, lines, curves and other statements don't affect triangle meshes.
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn parse_face<'a>(&mut self, tokens: impl Iterator<Item = &'a str>) -> Result<(), LineError> {
+        let corners = tokens
+            .map(|corner| self.parse_corner(corner))
+            .collect::<Result<Vec<_>, _>>()?;
+        if corners.len() < 3 {
+            return Err(LineError::Parse(format!(
+                "face has {} vertices, at least 3 are needed",
+                corners.len()
+            )));
+        }
+
+        if self.vertex_indices.is_empty() {
+            self.model.meshes.push(Mesh {
+                name: self.group.clone(),
+                vertices: Vec::new(),
+                indices: Vec::new(),
+                material: self.material,
+            });
+        }
+        let mesh = self.model.meshes.last_mut().unwrap();
+        for corner in fan_triangulate(&corners) {
+            let index = *self.vertex_indices.entry(corner).or_insert_with(|| {
+                let (position, uv, normal) = corner;
+                let vertex = Vertex::new(
+                    self.positions[position],
+                    WHITE,
+                    uv.map_or(Vec2::zeros(), |uv| self.uvs[uv]),
+                );
+                mesh.vertices
+                    .push(vertex.with_normal(normal.map_or(Vec3::zeros(), |n| self.normals[n])));
+                mesh.vertices.len() as u32 - 1
+            });
+            mesh.indices.push(index);
+        }
+        Ok(())
+    }
+
+    /// Parses a face corner of the form `v`, `v/vt`, `v//vn` or `v/vt/vn`.
+    fn parse_corner(
+        &self,
+        corner: &str,
+    ) -> Result<(usize, Option<usize>, Option<usize>), LineError> {
+        let mut indices = corner.split('/');
+        let mut next =
+            |count: usize, kind: &str| match indices.next().filter(|index| !index.is_empty()) {
+                Some(index) => resolve_index(index, count, kind).map(Some),
+                None => Ok(None),
+            };
+        let position = next(self.positions.len(), "position")?
+            .ok_or_else(|| LineError::Parse(format!("face vertex `{corner}` has no position")))?;
+        let uv = next(self.uvs.len(), "texture coordinate")?;
+        let normal = next(self.normals.len(), "normal")?;
+        Ok((position, uv, normal))
+    }
+}
+
+fn parse_mtl(source: &str, path: &Path, materials: &mut Vec<Material>) -> Result<(), ObjError> {
+    for (i, line) in source.lines().enumerate() {
+        let parse_line = |materials: &mut Vec<Material>| -> Result<(), LineError> {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut tokens = line.split_whitespace();
+            let Some(keyword) = tokens.next() else {
+                return Ok(());
+            };
+            if keyword == "newmtl" {
+                let name = tokens.next().ok_or_else(|| missing("material name"))?;
+                materials.push(Material {
+                    name: name.to_owned(),
+                    diffuse_color: WHITE,
+                    diffuse_texture: None,
+                });
+                return Ok(());
+            }
+            let material = materials
+                .last_mut()
+                .ok_or_else(|| LineError::Parse(format!("`{keyword}` before `newmtl`")))?;
+            match keyword {
+                "Kd" => {
+                    let rgb = parse_floats(&mut tokens, 3)?;
+                    material.diffuse_color = Color::new(rgb[0], rgb[1], rgb[2], 1.0);
+                }
+                "d" => material.diffuse_color.w = parse_floats(&mut tokens, 1)?[0],
+                "Tr" => material.diffuse_color.w = 1.0 - parse_floats(&mut tokens, 1)?[0],
+                "map_Kd" => {
+                    // Options come before the file name, which is the last argument.
+                    let file = tokens.last().ok_or_else(|| missing("texture file"))?;
+                    let texture_path = resolve(path, file);
+                    let texture = Image::from_file(texture_path.clone()).map_err(|source| {
+                        ObjError::Texture {
+                            path: texture_path,
+                            source,
+                        }
+                    })?;
+                    material.diffuse_texture = Some(texture);
+                }
+                // Other properties are not used by the pipeline.
+                _ => {}
+            }
+            Ok(())
+        };
+        parse_line(materials).map_err(|error| error.at(path, i + 1))?;
+    }
+    Ok(())
+}
+
+fn parse_floats<'a>(
+    tokens: &mut impl Iterator<Item = &'a str>,
+    count: usize,
+) -> Result<Vec<f32>, LineError> {
+    let values = tokens
+        .map(|token| {
+            token
+                .parse::<f32>()
+                .map_err(|_| LineError::Parse(format!("invalid number `{token}`")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if values.len() < count {
+        return Err(LineError::Parse(format!(
+            "expected {count} numbers, found {}",
+            values.len()
+        )));
+    }
+    Ok(values)
+}
+
+/// Converts a one-based, possibly negative (relative to the end) OBJ index.
+fn resolve_index(index: &str, count: usize, kind: &str) -> Result<usize, LineError> {
+    let value = index
+        .parse::<isize>()
+        .map_err(|_| LineError::Parse(format!("invalid {kind} index `{index}`")))?;
+    let resolved = if value < 0 {
+        count as isize + value
+    } else {
+        value - 1
+    };
+    if value == 0 || resolved < 0 || resolved as usize >= count {
+        return Err(LineError::Parse(format!(
+            "{kind} index {value} is out of range, {count} defined so far"
+        )));
+    }
+    Ok(resolved as usize)
+}
+
+fn missing(what: &str) -> LineError {
+    LineError::Parse(format!("missing {what}"))
+}
+
+/// Resolves a path referenced from the file at `from`.
+fn resolve(from: &Path, path: &str) -> PathBuf {
+    from.parent().unwrap_or(Path::new("")).join(path)
+}
+
+fn read_to_string(path: &Path) -> Result<String, ObjError> {
+    fs::read_to_string(path).map_err(|source| ObjError::Io {
+        path: path.to_owned(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polygons_are_triangulated_and_vertices_shared() {
+        let source = "
+            v 0 0 0
+            v 1 0 0
+            v 1 1 0
+            v 0 1 0
+            vt 0 0
+            vt 1 1
+            vn 0 0 1
+            g quad
+            f 1/1/1 2/1/1 3/2/1 -1/2/1
+        ";
+
+        let model = ObjModel::parse(source, Path::new("quad.obj")).unwrap();
+
+        assert_eq!(model.meshes.len(), 1);
+        let mesh = &model.meshes[0];
+        assert_eq!(mesh.name, "quad");
+        assert_eq!(mesh.indices, [0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.vertices[3].coords, vec3(0.0, 1.0, 0.0).push(1.0));
+        assert_eq!(mesh.vertices[2].uv, vec2(1.0, 0.0));
+        assert_eq!(mesh.vertices[0].normal, vec3(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn groups_split_meshes() {
+        let source = "v 0 0 0\nv 1 0 0\nv 0 1 0\ng a\nf 1 2 3\ng b\nf 3 2 1\nf 1//  2 3\n";
+
+        let model = ObjModel::parse(source, Path::new("groups.obj")).unwrap();
+
+        let names = model.meshes.iter().map(|m| m.name.as_str());
+        assert_eq!(names.collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(model.meshes[1].indices, [0, 1, 2, 2, 1, 0]);
+    }
+
+    #[test]
+    fn malformed_files_report_their_location() {
+        let error = |source| {
+            ObjModel::parse(source, Path::new("bad.obj"))
+                .err()
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(
+            error("v 0 0 0\nv 1 0\n"),
+            "bad.obj:2: expected 3 numbers, found 2"
+        );
+        assert_eq!(
+            error("v 0 0 0\nf 1 2 1\n"),
+            "bad.obj:2: position index 2 is out of range, 1 defined so far"
+        );
+        assert_eq!(error("vt a 0\n"), "bad.obj:1: invalid number `a`");
+        assert_eq!(error("usemtl red\n"), "bad.obj:1: unknown material `red`");
+    }
+
+    #[test]
+    fn materials_and_textures_are_relative_to_the_file() {
+        let directory = std::env::temp_dir().join("rasterization_obj_materials");
+        fs::create_dir_all(directory.join("textures")).unwrap();
+        Image::from_buffer(vec![0xFF00FF00], 1, 1)
+            .save(directory.join("textures/green.png"))
+            .unwrap();
+        fs::write(
+            directory.join("scene.mtl"),
+            "newmtl green\nKd 0.5 0.5 0.5\nmap_Kd -s 1 1 1 textures/green.png\n",
+        )
+        .unwrap();
+        fs::write(
+            directory.join("scene.obj"),
+            "mtllib scene.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl green\nf 1 2 3\n",
+        )
+        .unwrap();
+
+        let model = ObjModel::load(directory.join("scene.obj")).unwrap();
+        fs::remove_dir_all(directory).unwrap();
+
+        let material = &model.materials[model.meshes[0].material.unwrap()];
+        assert_eq!(material.name, "green");
+        assert_eq!(material.diffuse_color, Color::new(0.5, 0.5, 0.5, 1.0));
+        let texture = material.diffuse_texture.as_ref().unwrap();
+        assert_eq!(texture.get_color((0, 0)), crate::color::GREEN);
+    }
+}
@@ -1,71 +1,936 @@
-use nalgebra_glm::Mat4;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+};
+
+use nalgebra_glm::{vec2, Vec2, Vec3};
 
 use crate::{
-    clipping::clip_triangle,
-    framebuffer::Framebuffer,
-    image::Image,
-    rasterization::{rasterize_solid_triangle, Fragment},
-    sampler::Sampler,
+    clipping::{clip_line, clip_point, clip_triangle},
+    framebuffer::{Framebuffer, FramebufferBand},
+    rasterization::{
+        rasterize_line, rasterize_multisampled_triangle, rasterize_point, signed_area, Fragment,
+    },
+    shader::{FragmentInput, FragmentShader, VertexShader},
+    state::{BlendState, DepthStencilState, RasterizerState},
+    topology::{Primitive, PrimitiveTopology},
     triangulation::fan_triangulate,
-    vertex::Vertex,
-    viewport::Viewport,
+    varyings::Varyings,
+    vertex::ClipVertex,
+    viewport::{Rect, Viewport},
 };
 
+const TILE_SIZE: usize = 64;
+
+pub trait Index: Copy {
+    fn to_usize(self) -> usize;
+}
+
+impl Index for u16 {
+    fn to_usize(self) -> usize {
+        self as usize
+    }
+}
+
+impl Index for u32 {
+    fn to_usize(self) -> usize {
+        self as usize
+    }
+}
+
 #[derive(Debug)]
 pub struct RasterizationPipeline {
     viewport: Viewport,
+    point_size: f32,
+    rasterizer_state: RasterizerState,
+    depth_stencil_state: DepthStencilState,
+    blend_state: BlendState,
+    scissor: Option<Rect>,
+    sample_shading: bool,
+    thread_count: usize,
 }
 
 impl RasterizationPipeline {
     pub fn new(viewport: Viewport) -> Self {
-        Self { viewport }
+        Self {
+            viewport,
+            point_size: 1.0,
+            rasterizer_state: RasterizerState::default(),
+            depth_stencil_state: DepthStencilState::default(),
+            blend_state: BlendState::default(),
+            scissor: None,
+            sample_shading: false,
+            thread_count: 1,
+        }
+    }
+
+    /// Number of threads used for rasterization. With more than one, the framebuffer is split into
+    /// tiles that are rendered in parallel.
+    pub fn set_thread_count(&mut self, thread_count: usize) {
+        self.thread_count = thread_count.max(1);
+    }
+
+    pub fn thread_count(&self) -> usize {
+        self.thread_count
+    }
+
+    /// Restricts rendering to a rectangle of the framebuffer, or lifts the restriction if `None`.
+    pub fn set_scissor(&mut self, scissor: Option<Rect>) {
+        self.scissor = scissor;
+    }
+
+    pub fn scissor(&self) -> Option<&Rect> {
+        self.scissor.as_ref()
+    }
+
+    /// Runs the fragment shader once per covered sample of a multisampled framebuffer instead of
+    /// once per pixel.
+    pub fn set_sample_shading(&mut self, sample_shading: bool) {
+        self.sample_shading = sample_shading;
+    }
+
+    pub fn sample_shading(&self) -> bool {
+        self.sample_shading
+    }
+
+    pub fn set_rasterizer_state(&mut self, rasterizer_state: RasterizerState) {
+        self.rasterizer_state = rasterizer_state;
+    }
+
+    pub fn rasterizer_state(&self) -> &RasterizerState {
+        &self.rasterizer_state
+    }
+
+    pub fn set_depth_stencil_state(&mut self, depth_stencil_state: DepthStencilState) {
+        self.depth_stencil_state = depth_stencil_state;
+    }
+
+    pub fn depth_stencil_state(&self) -> &DepthStencilState {
+        &self.depth_stencil_state
+    }
+
+    pub fn set_blend_state(&mut self, blend_state: BlendState) {
+        self.blend_state = blend_state;
+    }
+
+    pub fn blend_state(&self) -> &BlendState {
+        &self.blend_state
+    }
+
+    pub fn set_point_size(&mut self, point_size: f32) {
+        self.point_size = point_size;
+    }
+
+    pub fn point_size(&self) -> f32 {
+        self.point_size
+    }
+
+    pub fn draw<VS, FS>(
+        &self,
+        framebuffer: &mut Framebuffer,
+        topology: PrimitiveTopology,
+        (vertex_shader, fragment_shader): (&VS, &FS),
+        vertices: &[VS::Input],
+    ) where
+        VS: VertexShader,
+        VS::Varyings: Send + Sync,
+        FS: FragmentShader<VS::Varyings> + Sync,
+    {
+        let mut cache = VertexCache::new(vertex_shader, vertices);
+        self.draw_primitives(
+            framebuffer,
+            topology,
+            fragment_shader,
+            vertices.len(),
+            |i| cache.get(i),
+        );
+    }
+
+    pub fn draw_indexed<VS, FS, I: Index>(
+        &self,
+        framebuffer: &mut Framebuffer,
+        topology: PrimitiveTopology,
+        (vertex_shader, fragment_shader): (&VS, &FS),
+        vertices: &[VS::Input],
+        indices: &[I],
+    ) where
+        VS: VertexShader,
+        VS::Varyings: Send + Sync,
+        FS: FragmentShader<VS::Varyings> + Sync,
+    {
+        let mut cache = VertexCache::new(vertex_shader, vertices);
+        self.draw_primitives(framebuffer, topology, fragment_shader, indices.len(), |i| {
+            cache.get(indices[i].to_usize())
+        });
     }
 
-    pub fn draw_triangles(
+    fn draw_primitives<V: Varyings + Send + Sync, FS: FragmentShader<V> + Sync>(
         &self,
         framebuffer: &mut Framebuffer,
-        transform: &Mat4,
-        (image, sampler): (&Image, &Sampler),
-        vertices: &[Vertex],
+        topology: PrimitiveTopology,
+        fragment_shader: &FS,
+        vertex_count: usize,
+        mut fetch: impl FnMut(usize) -> ClipVertex<V>,
     ) {
-        let primitive_count = vertices.len() / 3;
+        let mut bounds = Rect::new(0, 0, framebuffer.width(), framebuffer.height());
+        if let Some(scissor) = &self.scissor {
+            bounds = bounds.intersection(scissor);
+        }
+        if bounds.is_empty() {
+            return;
+        }
+        let mut primitives = Vec::new();
+        for primitive in topology.primitives(vertex_count) {
+            match primitive {
+                Primitive::Point(i) => self.setup_point(&mut primitives, &fetch(i)),
+                Primitive::Line(line) => self.setup_line(&mut primitives, &line.map(&mut fetch)),
+                Primitive::Triangle(triangle) => {
+                    self.setup_triangle(&mut primitives, &triangle.map(&mut fetch))
+                }
+            }
+        }
+        if self.thread_count > 1 {
+            self.rasterize_tiled(framebuffer, &bounds, fragment_shader, &primitives);
+        } else {
+            let mut band = framebuffer.as_band_mut();
+            for primitive in &primitives {
+                self.rasterize(&mut band, &bounds, fragment_shader, primitive);
+            }
+        }
+    }
+
+    fn setup_point<V: Varyings>(
+        &self,
+        primitives: &mut Vec<SetupPrimitive<V>>,
+        point: &ClipVertex<V>,
+    ) {
+        let Some(point) = clip_point(point) else {
+            return;
+        };
+        let (center, point) = self.to_framebuffer(point);
+        primitives.push(SetupPrimitive::Point {
+            center,
+            vertices: [point; 3],
+        });
+    }
+
+    fn setup_line<V: Varyings>(
+        &self,
+        primitives: &mut Vec<SetupPrimitive<V>>,
+        line: &[ClipVertex<V>; 2],
+    ) {
+        let Some(clipped_line) = clip_line(line) else {
+            return;
+        };
+        let [(p0, v0), (p1, v1)] = clipped_line.map(|v| self.to_framebuffer(v));
+        primitives.push(SetupPrimitive::Line {
+            coords: [p0, p1],
+            vertices: [v0, v1, v1],
+        });
+    }
+
+    fn setup_triangle<V: Varyings>(
+        &self,
+        primitives: &mut Vec<SetupPrimitive<V>>,
+        triangle: &[ClipVertex<V>; 3],
+    ) {
+        let clipped_polygon = clip_triangle(triangle);
+        let clipped_triangles = fan_triangulate(&clipped_polygon);
+        let primitive_count = clipped_triangles.len() / 3;
         for i in 0..primitive_count {
-            let triangle = [0, 1, 2]
-                .map(|j| vertices[3 * i + j])
-                .map(|v| v.transform(transform));
-            let clipped_polygon = clip_triangle(&triangle);
-            let clipped_triangles = fan_triangulate(&clipped_polygon);
-            let primitive_count = clipped_triangles.len() / 3;
-            for i in 0..primitive_count {
-                let ndc_triangle = [0, 1, 2]
-                    .map(|j| clipped_triangles[3 * i + j])
-                    .map(|v| v.homogenize());
-                let screen_coords = [0, 1, 2].map(|i| {
-                    self.viewport
-                        .ndc_to_framebuffer(ndc_triangle[i].coords.xy())
-                });
-                let [v0, v1, v2] = ndc_triangle;
-                rasterize_solid_triangle(
-                    &screen_coords,
-                    |Fragment {
-                         coords,
-                         t,
-                         dt_dx,
-                         dt_dy,
-                     }| {
-                        let screen_coords = (coords.x as usize, coords.y as usize);
-                        let Vertex { coords, uv, .. } = v0.bary_lerp(&v1, &v2, t);
-                        let duv_dx = v0.duv(&v1, &v2, t, dt_dx);
-                        let duv_dy = v0.duv(&v1, &v2, t, dt_dy);
-                        if framebuffer.test_and_set_depth_safe(screen_coords, coords.z) {
-                            framebuffer.set_color(
-                                screen_coords,
-                                sampler.sample(image, uv, duv_dx, duv_dy),
-                            );
+            let [(p0, v0), (p1, v1), (p2, v2)] = [0, 1, 2]
+                .map(|j| clipped_triangles[3 * i + j])
+                .map(|v| self.to_framebuffer(v));
+            let coords = [p0, p1, p2];
+            let front_facing = self.rasterizer_state.is_front_facing(signed_area(&coords));
+            if self.rasterizer_state.is_culled(front_facing) {
+                continue;
+            }
+            primitives.push(SetupPrimitive::Triangle {
+                coords,
+                vertices: [v0, v1, v2],
+                front_facing,
+            });
+        }
+    }
+
+    /// Sorts the primitives into square tiles of the framebuffer, then rasterizes bands of tiles in
+    /// parallel. Each pixel still sees the primitives in submission order, so the output matches
+    /// the serial path.
+    fn rasterize_tiled<V: Varyings + Send + Sync, FS: FragmentShader<V> + Sync>(
+        &self,
+        framebuffer: &mut Framebuffer,
+        bounds: &Rect,
+        fragment_shader: &FS,
+        primitives: &[SetupPrimitive<V>],
+    ) {
+        let tile_columns = framebuffer.width().div_ceil(TILE_SIZE);
+        let tile_rows = framebuffer.height().div_ceil(TILE_SIZE);
+        let mut bins = vec![Vec::new(); tile_columns * tile_rows];
+        for (i, primitive) in primitives.iter().enumerate() {
+            let (min, max) = primitive.screen_bounds(self.point_size);
+            let (first, last) = (
+                vec2(bounds.x, bounds.y),
+                vec2(bounds.x + bounds.width - 1, bounds.y + bounds.height - 1),
+            );
+            if (0..2).any(|axis| max[axis] < first[axis] as f32 || min[axis] > last[axis] as f32) {
+                continue;
+            }
+            // The bounds lie within the framebuffer, so the tiles do as well.
+            let tile = |c: f32, axis: usize| {
+                (c.max(0.0) as usize).clamp(first[axis], last[axis]) / TILE_SIZE
+            };
+            let column_range = tile(min.x, 0)..=tile(max.x, 0);
+            let row_range = tile(min.y, 1)..=tile(max.y, 1);
+            for row in row_range {
+                for column in column_range.clone() {
+                    bins[row * tile_columns + column].push(i);
+                }
+            }
+        }
+
+        let bands = framebuffer
+            .split_bands_mut(TILE_SIZE)
+            .into_iter()
+            .map(Mutex::new)
+            .collect::<Vec<_>>();
+        let next_band = AtomicUsize::new(0);
+        std::thread::scope(|scope| {
+            for _ in 0..self.thread_count.min(bands.len()) {
+                scope.spawn(|| loop {
+                    let row = next_band.fetch_add(1, Ordering::Relaxed);
+                    let Some(band) = bands.get(row) else {
+                        break;
+                    };
+                    let mut band = band.lock().unwrap();
+                    for column in 0..tile_columns {
+                        let tile =
+                            Rect::new(column * TILE_SIZE, row * TILE_SIZE, TILE_SIZE, TILE_SIZE)
+                                .intersection(&band.bounds())
+                                .intersection(bounds);
+                        if tile.is_empty() {
+                            continue;
                         }
-                    },
-                );
+                        for &i in &bins[row * tile_columns + column] {
+                            self.rasterize(&mut band, &tile, fragment_shader, &primitives[i]);
+                        }
+                    }
+                });
             }
+        });
+    }
+
+    fn rasterize<V: Varyings, FS: FragmentShader<V>>(
+        &self,
+        band: &mut FramebufferBand,
+        bounds: &Rect,
+        fragment_shader: &FS,
+        primitive: &SetupPrimitive<V>,
+    ) {
+        match primitive {
+            SetupPrimitive::Point { center, vertices } => {
+                rasterize_point(*center, self.point_size, bounds, |fragment| {
+                    self.shade_fragment(band, fragment_shader, vertices, true, fragment)
+                })
+            }
+            SetupPrimitive::Line { coords, vertices } => {
+                rasterize_line(coords, bounds, |fragment| {
+                    self.shade_fragment(band, fragment_shader, vertices, true, fragment)
+                })
+            }
+            SetupPrimitive::Triangle {
+                coords,
+                vertices,
+                front_facing,
+            } => rasterize_multisampled_triangle(coords, bounds, band.sample_count(), |fragment| {
+                self.shade_fragment(band, fragment_shader, vertices, *front_facing, fragment)
+            }),
         }
     }
+
+    /// Performs the perspective division and the viewport transform, returning the coordinates in
+    /// the framebuffer along with the homogenized vertex.
+    fn to_framebuffer<V: Varyings>(&self, vertex: ClipVertex<V>) -> (Vec2, ClipVertex<V>) {
+        let mut vertex = vertex.homogenize();
+        vertex.position.z = self.viewport.ndc_depth_to_framebuffer(vertex.position.z);
+        (
+            self.viewport.ndc_to_framebuffer(vertex.position.xy()),
+            vertex,
+        )
+    }
+
+    /// Runs the stencil and depth tests for every covered sample, then shades the fragment, once or
+    /// per sample, and writes its color to the samples that passed.
+    fn shade_fragment<V: Varyings, FS: FragmentShader<V>>(
+        &self,
+        framebuffer: &mut FramebufferBand,
+        fragment_shader: &FS,
+        primitive: &[ClipVertex<V>; 3],
+        front_facing: bool,
+        Fragment {
+            coords,
+            coverage,
+            t,
+            dt_dx,
+            dt_dy,
+        }: Fragment,
+    ) {
+        let fragment = FragmentInput::new(coords, primitive, t, dt_dx, dt_dy, front_facing);
+        let screen_coords = (coords.x, coords.y);
+        let samples = framebuffer.sample_count();
+        let depth_stencil_state = &self.depth_stencil_state;
+        let stencil_face = depth_stencil_state.stencil_face(front_facing);
+        let update_stencil = |framebuffer: &mut FramebufferBand, sample, op| {
+            if depth_stencil_state.stencil_test_enable {
+                let stored = framebuffer.get_stencil(screen_coords, sample);
+                framebuffer.set_stencil(screen_coords, sample, stencil_face.update(op, stored));
+            }
+        };
+        let z = Vec3::new(
+            primitive[0].position.z,
+            primitive[1].position.z,
+            primitive[2].position.z,
+        );
+        let depth_gradient = vec2(dt_dx.dot(&z), dt_dy.dot(&z));
+
+        let mut passed = 0;
+        for sample in 0..samples.count() {
+            if coverage & (1 << sample) == 0 {
+                continue;
+            }
+            if depth_stencil_state.stencil_test_enable
+                && !stencil_face.test(framebuffer.get_stencil(screen_coords, sample))
+            {
+                update_stencil(framebuffer, sample, stencil_face.fail_op);
+                continue;
+            }
+            let depth = fragment.depth + samples.offset(sample).dot(&depth_gradient);
+            if depth_stencil_state.depth_test_enable
+                && !framebuffer.test_and_set_depth(
+                    screen_coords,
+                    sample,
+                    depth,
+                    depth_stencil_state.depth_compare_op,
+                    depth_stencil_state.depth_write_enable,
+                )
+            {
+                update_stencil(framebuffer, sample, stencil_face.depth_fail_op);
+                continue;
+            }
+            update_stencil(framebuffer, sample, stencil_face.pass_op);
+            passed |= 1 << sample;
+        }
+        if passed == 0 {
+            return;
+        }
+
+        let pixel_color = (!self.sample_shading).then(|| fragment_shader.shade(&fragment));
+        for sample in 0..samples.count() {
+            if passed & (1 << sample) == 0 {
+                continue;
+            }
+            let color = pixel_color.unwrap_or_else(|| {
+                let offset = samples.offset(sample);
+                let t = t + dt_dx * offset.x + dt_dy * offset.y;
+                fragment_shader.shade(&FragmentInput::new(
+                    coords,
+                    primitive,
+                    t,
+                    dt_dx,
+                    dt_dy,
+                    front_facing,
+                ))
+            });
+            if self.blend_state.reads_destination() {
+                let dst = framebuffer.get_color(screen_coords, sample);
+                framebuffer.set_color(screen_coords, sample, self.blend_state.blend(color, dst));
+            } else {
+                framebuffer.set_color(screen_coords, sample, color);
+            }
+        }
+    }
+}
+
+/// Primitive after clipping and the viewport transform, ready to be rasterized.
+enum SetupPrimitive<V> {
+    Point {
+        center: Vec2,
+        vertices: [ClipVertex<V>; 3],
+    },
+    Line {
+        coords: [Vec2; 2],
+        vertices: [ClipVertex<V>; 3],
+    },
+    Triangle {
+        coords: [Vec2; 3],
+        vertices: [ClipVertex<V>; 3],
+        front_facing: bool,
+    },
+}
+
+impl<V> SetupPrimitive<V> {
+    /// Conservative bounding box of the primitive in the framebuffer.
+    fn screen_bounds(&self, point_size: f32) -> (Vec2, Vec2) {
+        match self {
+            SetupPrimitive::Point { center, .. } => (
+                center.add_scalar(-point_size / 2.0 - 1.0),
+                center.add_scalar(point_size / 2.0 + 1.0),
+            ),
+            SetupPrimitive::Line {
+                coords: [p0, p1], ..
+            } => (p0.inf(p1).add_scalar(-1.0), p0.sup(p1).add_scalar(1.0)),
+            SetupPrimitive::Triangle {
+                coords: [p0, p1, p2],
+                ..
+            } => (
+                p0.inf(&p1.inf(p2)).add_scalar(-1.0),
+                p0.sup(&p1.sup(p2)).add_scalar(1.0),
+            ),
+        }
+    }
+}
+
+/// Post-transform vertex cache, so that vertices shared between primitives are shaded only once.
+struct VertexCache<'a, VS: VertexShader> {
+    vertex_shader: &'a VS,
+    vertices: &'a [VS::Input],
+    entries: Vec<Option<ClipVertex<VS::Varyings>>>,
+}
+
+impl<'a, VS: VertexShader> VertexCache<'a, VS> {
+    fn new(vertex_shader: &'a VS, vertices: &'a [VS::Input]) -> Self {
+        Self {
+            vertex_shader,
+            vertices,
+            entries: vec![None; vertices.len()],
+        }
+    }
+
+    fn get(&mut self, index: usize) -> ClipVertex<VS::Varyings> {
+        *self.entries[index].get_or_insert_with(|| self.vertex_shader.shade(&self.vertices[index]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use nalgebra_glm::{vec2, vec3};
+
+    use super::*;
+    use crate::{
+        color::{Color, BLACK, RED, WHITE},
+        framebuffer::ColorFormat,
+        model::unit_cube_indexed,
+        multisample::SampleCount,
+        shader::{FlatColorShader, TransformShader, VertexColorShader},
+        state::{BlendState, CompareOp, CullMode, FrontFace, StencilFaceState, StencilOp},
+        vertex::Vertex,
+    };
+
+    struct CountingShader<'a> {
+        inner: TransformShader,
+        count: &'a Cell<usize>,
+    }
+
+    impl VertexShader for CountingShader<'_> {
+        type Input = Vertex;
+        type Varyings = Vertex;
+
+        fn shade(&self, input: &Vertex) -> ClipVertex<Vertex> {
+            self.count.set(self.count.get() + 1);
+            self.inner.shade(input)
+        }
+    }
+
+    #[test]
+    fn indexed_vertices_are_shaded_once() {
+        let mut framebuffer = Framebuffer::new(8, 8);
+        let pipeline = RasterizationPipeline::new(Viewport::full(8.0, 8.0));
+        let count = Cell::new(0);
+        let vertex_shader = CountingShader {
+            inner: TransformShader::new(nalgebra_glm::scale(
+                &nalgebra_glm::identity(),
+                &nalgebra_glm::vec3(1.0, 1.0, 0.5),
+            )),
+            count: &count,
+        };
+        let (vertices, indices) = unit_cube_indexed(|_, c| Vertex::new(c, WHITE, vec2(0.0, 0.0)));
+
+        framebuffer.clear(BLACK, f32::INFINITY);
+        pipeline.draw_indexed(
+            &mut framebuffer,
+            PrimitiveTopology::TriangleList,
+            (&vertex_shader, &FlatColorShader::new(WHITE)),
+            &vertices,
+            &indices,
+        );
+
+        assert_eq!(count.get(), vertices.len());
+        assert_eq!(framebuffer.get_color((4, 4)), WHITE);
+    }
+
+    #[test]
+    fn line_strips_share_vertices() {
+        let mut framebuffer = Framebuffer::new(4, 4);
+        let pipeline = RasterizationPipeline::new(Viewport::full(4.0, 4.0));
+        let vertices = [vec2(-0.75, -0.75), vec2(0.75, -0.75), vec2(0.75, 0.75)]
+            .map(|c| Vertex::new(c.push(0.5), WHITE, c));
+
+        framebuffer.clear(BLACK, f32::INFINITY);
+        pipeline.draw(
+            &mut framebuffer,
+            PrimitiveTopology::LineStrip,
+            (
+                &TransformShader::new(nalgebra_glm::identity()),
+                &FlatColorShader::new(WHITE),
+            ),
+            &vertices,
+        );
+
+        let lit = (0..4)
+            .flat_map(|y| (0..4).map(move |x| (x, y)))
+            .filter(|&c| framebuffer.get_color(c) == WHITE)
+            .collect::<Vec<_>>();
+        assert_eq!(lit, [(0, 0), (1, 0), (2, 0), (3, 0), (3, 1), (3, 2)]);
+    }
+
+    struct FacingShader;
+
+    impl FragmentShader<Vertex> for FacingShader {
+        fn shade(&self, fragment: &FragmentInput<Vertex>) -> Color {
+            if fragment.front_facing {
+                WHITE
+            } else {
+                RED
+            }
+        }
+    }
+
+    #[test]
+    fn back_faces_are_culled_unless_disabled() {
+        let mut framebuffer = Framebuffer::new(4, 4);
+        let mut pipeline = RasterizationPipeline::new(Viewport::full(4.0, 4.0));
+        let clockwise = [vec2(-1.0, -1.0), vec2(1.0, -1.0), vec2(-1.0, 1.0)]
+            .map(|c| Vertex::new(c.push(0.5), WHITE, c));
+        let shaders = (
+            &TransformShader::new(nalgebra_glm::identity()),
+            &FacingShader,
+        );
+
+        framebuffer.clear(BLACK, f32::INFINITY);
+        pipeline.draw(
+            &mut framebuffer,
+            PrimitiveTopology::TriangleList,
+            shaders,
+            &clockwise,
+        );
+        assert_eq!(framebuffer.get_color((0, 0)), BLACK);
+
+        pipeline.set_rasterizer_state(RasterizerState::new(
+            CullMode::None,
+            FrontFace::CounterClockwise,
+        ));
+        pipeline.draw(
+            &mut framebuffer,
+            PrimitiveTopology::TriangleList,
+            shaders,
+            &clockwise,
+        );
+        assert_eq!(framebuffer.get_color((0, 0)), RED);
+
+        framebuffer.clear(BLACK, f32::INFINITY);
+        pipeline.set_rasterizer_state(RasterizerState::new(CullMode::Back, FrontFace::Clockwise));
+        pipeline.draw(
+            &mut framebuffer,
+            PrimitiveTopology::TriangleList,
+            shaders,
+            &clockwise,
+        );
+        assert_eq!(framebuffer.get_color((0, 0)), WHITE);
+    }
+
+    #[test]
+    fn depth_range_and_compare_op() {
+        let mut framebuffer = Framebuffer::new(4, 4);
+        let mut pipeline =
+            RasterizationPipeline::new(Viewport::full(4.0, 4.0).with_depth_range(1.0, 0.0));
+        let quad = |z: f32| {
+            [vec2(-1.0, -1.0), vec2(-1.0, 1.0), vec2(1.0, -1.0)]
+                .map(|c| Vertex::new(c.push(z), WHITE, c))
+        };
+        let transform = TransformShader::new(nalgebra_glm::identity());
+
+        framebuffer.clear(BLACK, 0.0);
+        pipeline.set_depth_stencil_state(DepthStencilState::new(CompareOp::Greater, false));
+        pipeline.draw(
+            &mut framebuffer,
+            PrimitiveTopology::TriangleList,
+            (&transform, &FlatColorShader::new(RED)),
+            &quad(0.25),
+        );
+        assert_eq!(framebuffer.get_color((0, 0)), RED);
+        assert_eq!(framebuffer.get_depth((0, 0)), 0.0);
+
+        pipeline.set_depth_stencil_state(DepthStencilState::new(CompareOp::Greater, true));
+        pipeline.draw(
+            &mut framebuffer,
+            PrimitiveTopology::TriangleList,
+            (&transform, &FlatColorShader::new(WHITE)),
+            &quad(0.25),
+        );
+        pipeline.draw(
+            &mut framebuffer,
+            PrimitiveTopology::TriangleList,
+            (&transform, &FlatColorShader::new(RED)),
+            &quad(0.5),
+        );
+        assert_eq!(framebuffer.get_color((0, 0)), WHITE);
+        assert_eq!(framebuffer.get_depth((0, 0)), 0.75);
+    }
+
+    #[test]
+    fn stencil_masks_out_marked_pixels() {
+        let mut framebuffer = Framebuffer::new(4, 4);
+        let mut pipeline = RasterizationPipeline::new(Viewport::full(4.0, 4.0));
+        let triangle = |size: f32| {
+            [vec2(-1.0, -1.0), vec2(-1.0, size), vec2(size, -1.0)]
+                .map(|c| Vertex::new(c.push(0.5), WHITE, c))
+        };
+        let transform = TransformShader::new(nalgebra_glm::identity());
+        let mark = StencilFaceState::new(CompareOp::Always, 1).with_ops(
+            StencilOp::Keep,
+            StencilOp::Keep,
+            StencilOp::Replace,
+        );
+        let outside = StencilFaceState::new(CompareOp::NotEqual, 1);
+
+        framebuffer.clear(BLACK, f32::INFINITY);
+        framebuffer.clear_stencil(0);
+        pipeline.set_depth_stencil_state(DepthStencilState::disabled().with_stencil(mark, mark));
+        pipeline.draw(
+            &mut framebuffer,
+            PrimitiveTopology::TriangleList,
+            (&transform, &FlatColorShader::new(RED)),
+            &triangle(0.0),
+        );
+        pipeline
+            .set_depth_stencil_state(DepthStencilState::disabled().with_stencil(outside, outside));
+        pipeline.draw(
+            &mut framebuffer,
+            PrimitiveTopology::TriangleList,
+            (&transform, &FlatColorShader::new(WHITE)),
+            &triangle(3.0),
+        );
+
+        assert_eq!(framebuffer.get_stencil((0, 0)), 1);
+        assert_eq!(framebuffer.get_color((0, 0)), RED);
+        assert_eq!(framebuffer.get_stencil((1, 1)), 0);
+        assert_eq!(framebuffer.get_color((1, 1)), WHITE);
+    }
+
+    #[test]
+    fn scissor_restricts_fragments() {
+        let mut framebuffer = Framebuffer::new(4, 4);
+        let mut pipeline = RasterizationPipeline::new(Viewport::full(4.0, 4.0));
+        let vertices = [vec2(-1.0, -1.0), vec2(-1.0, 3.0), vec2(3.0, -1.0)]
+            .map(|c| Vertex::new(c.push(0.5), WHITE, c));
+
+        framebuffer.clear(BLACK, f32::INFINITY);
+        pipeline.set_scissor(Some(Rect::new(1, 2, 2, 1)));
+        pipeline.draw(
+            &mut framebuffer,
+            PrimitiveTopology::TriangleList,
+            (
+                &TransformShader::new(nalgebra_glm::identity()),
+                &FlatColorShader::new(WHITE),
+            ),
+            &vertices,
+        );
+
+        let lit = (0..4)
+            .flat_map(|y| (0..4).map(move |x| (x, y)))
+            .filter(|&c| framebuffer.get_color(c) == WHITE)
+            .collect::<Vec<_>>();
+        assert_eq!(lit, [(1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn tiled_rendering_matches_serial_rendering() {
+        let (width, height) = (150, 100);
+        let mut seed = 1u32;
+        let mut random = move || {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            (seed >> 8) as f32 / (1 << 24) as f32
+        };
+        let vertices = (0..300)
+            .map(|_| {
+                let coords = vec3(random(), random(), random()) * 2.4 - vec3(1.2, 1.2, 0.2);
+                let color = Color::new(random(), random(), random(), random());
+                Vertex::new(coords, color, coords.xy())
+            })
+            .collect::<Vec<_>>();
+        let render = |thread_count, scissor| {
+            let mut framebuffer = Framebuffer::new(width, height);
+            let mut pipeline =
+                RasterizationPipeline::new(Viewport::full(width as f32, height as f32));
+            pipeline.set_thread_count(thread_count);
+            pipeline.set_scissor(scissor);
+            pipeline.set_rasterizer_state(RasterizerState::new(
+                CullMode::None,
+                FrontFace::CounterClockwise,
+            ));
+            pipeline.set_blend_state(BlendState::alpha_blending());
+            framebuffer.clear(BLACK, f32::INFINITY);
+            let shaders = (
+                &TransformShader::new(nalgebra_glm::identity()),
+                &VertexColorShader,
+            );
+            pipeline.draw(
+                &mut framebuffer,
+                PrimitiveTopology::TriangleList,
+                shaders,
+                &vertices,
+            );
+            pipeline.draw(
+                &mut framebuffer,
+                PrimitiveTopology::LineStrip,
+                shaders,
+                &vertices[..40],
+            );
+            (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|c| (framebuffer.get_color(c), framebuffer.get_depth(c)))
+                .collect::<Vec<_>>()
+        };
+
+        for scissor in [None, Some(Rect::new(37, 21, 50, 40))] {
+            assert_eq!(render(1, scissor), render(4, scissor));
+        }
+    }
+
+    #[test]
+    fn multisampled_edges_resolve_to_partial_coverage() {
+        let mut framebuffer = Framebuffer::new_multisampled(4, 4, SampleCount::X4);
+        let mut resolved = Framebuffer::new(4, 4);
+        let pipeline = RasterizationPipeline::new(Viewport::full(4.0, 4.0));
+        let vertices = [vec2(-1.0, -1.0), vec2(-1.0, 1.0), vec2(1.0, -1.0)]
+            .map(|c| Vertex::new(c.push(0.5), WHITE, c));
+
+        framebuffer.clear(BLACK, f32::INFINITY);
+        pipeline.draw(
+            &mut framebuffer,
+            PrimitiveTopology::TriangleList,
+            (
+                &TransformShader::new(nalgebra_glm::identity()),
+                &FlatColorShader::new(WHITE),
+            ),
+            &vertices,
+        );
+        framebuffer.resolve(&mut resolved);
+
+        assert_eq!(resolved.get_color((0, 0)), WHITE);
+        assert_eq!(resolved.get_color((3, 3)), BLACK);
+        let edge = resolved.get_color((1, 2));
+        assert!(edge.x > 0.0 && edge.x < 1.0);
+        assert_eq!(framebuffer.get_sample_depth((1, 2), 0), 0.5);
+        assert_eq!(framebuffer.get_sample_depth((1, 2), 3), f32::INFINITY);
+    }
+
+    #[test]
+    fn sample_shading_shades_each_sample() {
+        struct SampleXShader;
+
+        impl FragmentShader<Vertex> for SampleXShader {
+            fn shade(&self, fragment: &FragmentInput<Vertex>) -> Color {
+                Color::new(fragment.varyings.uv.x, 0.0, 0.0, 1.0)
+            }
+        }
+
+        let mut framebuffer = Framebuffer::new_multisampled(4, 4, SampleCount::X4);
+        let mut pipeline = RasterizationPipeline::new(Viewport::full(4.0, 4.0));
+        pipeline.set_rasterizer_state(RasterizerState::new(
+            CullMode::None,
+            FrontFace::CounterClockwise,
+        ));
+        let vertices = [vec2(-1.0, -1.0), vec2(-1.0, 3.0), vec2(3.0, -1.0)]
+            .map(|c| Vertex::new(c.push(0.5), WHITE, (c + vec2(1.0, 1.0)) / 8.0));
+        let shaders = (
+            &TransformShader::new(nalgebra_glm::identity()),
+            &SampleXShader,
+        );
+        let sample_colors = |framebuffer: &Framebuffer| {
+            (0..4)
+                .map(|sample| framebuffer.get_sample_color((1, 1), sample).x)
+                .collect::<Vec<_>>()
+        };
+
+        framebuffer.clear(BLACK, f32::INFINITY);
+        pipeline.draw(
+            &mut framebuffer,
+            PrimitiveTopology::TriangleList,
+            shaders,
+            &vertices,
+        );
+        let per_pixel = sample_colors(&framebuffer);
+        assert!(per_pixel.iter().all(|&c| c == per_pixel[0]));
+
+        framebuffer.clear(BLACK, f32::INFINITY);
+        pipeline.set_sample_shading(true);
+        pipeline.draw(
+            &mut framebuffer,
+            PrimitiveTopology::TriangleList,
+            shaders,
+            &vertices,
+        );
+        let per_sample = sample_colors(&framebuffer);
+        // Samples 0 and 2 lie left of the pixel center, samples 1 and 3 right of it.
+        assert!(per_sample[0] < per_pixel[0] && per_sample[2] < per_pixel[0]);
+        assert!(per_sample[1] > per_pixel[0] && per_sample[3] > per_pixel[0]);
+    }
+
+    #[test]
+    fn float_render_targets_accumulate_above_one() {
+        let vertices = [
+            vec3(-1.0, -1.0, 0.5),
+            vec3(3.0, -1.0, 0.5),
+            vec3(-1.0, 3.0, 0.5),
+        ]
+        .map(|c| Vertex::new(c, WHITE, c.xy()));
+        let render = |format| {
+            let mut framebuffer = Framebuffer::new(100, 100).with_color_format(format);
+            let mut pipeline = RasterizationPipeline::new(Viewport::full(100.0, 100.0));
+            pipeline.set_thread_count(4);
+            pipeline.set_rasterizer_state(RasterizerState::new(
+                CullMode::None,
+                FrontFace::CounterClockwise,
+            ));
+            pipeline.set_depth_stencil_state(DepthStencilState::disabled());
+            pipeline.set_blend_state(BlendState::additive());
+            framebuffer.clear(BLACK, f32::INFINITY);
+            for _ in 0..3 {
+                pipeline.draw(
+                    &mut framebuffer,
+                    PrimitiveTopology::TriangleList,
+                    (
+                        &TransformShader::new(nalgebra_glm::identity()),
+                        &FlatColorShader::new(Color::new(0.75, 0.5, 0.25, 1.0)),
+                    ),
+                    &vertices,
+                );
+            }
+            framebuffer.get_color((70, 90))
+        };
+
+        // 8-bit targets saturate.
+        assert_eq!(render(ColorFormat::Rgba8).xy(), vec2(1.0, 1.0));
+        assert_eq!(
+            render(ColorFormat::Rgba16F),
+            Color::new(2.25, 1.5, 0.75, 4.0)
+        );
+        assert_eq!(
+            render(ColorFormat::Rgba32F),
+            Color::new(2.25, 1.5, 0.75, 4.0)
+        );
+    }
 }
@@ -0,0 +1,441 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    fs, io,
+    path::Path,
+};
+
+use nalgebra_glm::{vec2, Vec3};
+
+use crate::{color::WHITE, triangulation::fan_triangulate, vertex::Vertex};
+
+#[derive(Debug)]
+pub enum PlyError {
+    Io(io::Error),
+    Parse(String),
+}
+
+impl Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlyError::Io(error) => write!(f, "cannot read PLY file: {error}"),
+            PlyError::Parse(message) => write!(f, "invalid PLY file: {message}"),
+        }
+    }
+}
+
+impl Error for PlyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PlyError::Io(error) => Some(error),
+            PlyError::Parse(_) => None,
+        }
+    }
+}
+
+/// Loads the vertices and triangulated faces of an ASCII or binary PLY file.
+pub fn load_ply(path: impl AsRef<Path>) -> Result<(Vec<Vertex>, Vec<u32>), PlyError> {
+    parse_ply(&fs::read(path).map_err(PlyError::Io)?)
+}
+
+/// Parses a PLY file. Vertex colors, normals and texture coordinates are read when present;
+/// elements other than vertices and faces are skipped.
+pub fn parse_ply(data: &[u8]) -> Result<(Vec<Vertex>, Vec<u32>), PlyError> {
+    let (header, body) = parse_header(data)?;
+    let mut reader = BodyReader {
+        data: body,
+        position: 0,
+        format: header.format,
+    };
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for element in &header.elements {
+        for _ in 0..element.count {
+            match element.name.as_str() {
+                "vertex" => vertices.push(read_vertex(&mut reader, &element.properties)?),
+                "face" => read_face(&mut reader, &element.properties, &mut indices)?,
+                _ => {
+                    for property in &element.properties {
+                        reader.read_property(property)?;
+                    }
+                }
+            }
+        }
+    }
+    if let Some(&index) = indices.iter().find(|&&i| i as usize >= vertices.len()) {
+        return Err(parse_error(format!(
+            "face refers to vertex {index}, but there are {}",
+            vertices.len()
+        )));
+    }
+    Ok((vertices, indices))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Result<Self, PlyError> {
+        Ok(match name {
+            "char" | "int8" => ScalarType::I8,
+            "uchar" | "uint8" => ScalarType::U8,
+            "short" | "int16" => ScalarType::I16,
+            "ushort" | "uint16" => ScalarType::U16,
+            "int" | "int32" => ScalarType::I32,
+            "uint" | "uint32" => ScalarType::U32,
+            "float" | "float32" => ScalarType::F32,
+            "double" | "float64" => ScalarType::F64,
+            _ => return Err(parse_error(format!("unknown property type `{name}`"))),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::I8 | ScalarType::U8 => 1,
+            ScalarType::I16 | ScalarType::U16 => 2,
+            ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => 4,
+            ScalarType::F64 => 8,
+        }
+    }
+
+    /// Largest value of integer types, used to normalize colors.
+    fn max(self) -> f64 {
+        match self {
+            ScalarType::I8 => i8::MAX as f64,
+            ScalarType::U8 => u8::MAX as f64,
+            ScalarType::I16 => i16::MAX as f64,
+            ScalarType::U16 => u16::MAX as f64,
+            ScalarType::I32 => i32::MAX as f64,
+            ScalarType::U32 => u32::MAX as f64,
+            ScalarType::F32 | ScalarType::F64 => 1.0,
+        }
+    }
+}
+
+struct Property {
+    name: String,
+    data_type: ScalarType,
+    /// Type of the length prefix of list properties.
+    count_type: Option<ScalarType>,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+}
+
+fn parse_header(data: &[u8]) -> Result<(Header, &[u8]), PlyError> {
+    // Only a whole `end_header` line ends the header, comments may mention it too.
+    let mut end = 0;
+    let body_start = loop {
+        if end == data.len() {
+            return Err(parse_error("missing `end_header`"));
+        }
+        let line_end = data[end..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(data.len(), |i| end + i + 1);
+        if data[end..line_end].trim_ascii() == b"end_header" {
+            break line_end;
+        }
+        end = line_end;
+    };
+    let text =
+        std::str::from_utf8(&data[..end]).map_err(|_| parse_error("header is not valid UTF-8"))?;
+
+    let mut lines = text.lines().map(str::trim);
+    if lines.next() != Some("ply") {
+        return Err(parse_error("missing `ply` magic number"));
+    }
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines {
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        match tokens[..] {
+            ["format", name, _version] => {
+                format = Some(match name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(parse_error(format!("unknown format `{name}`"))),
+                })
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_owned(),
+                count: count
+                    .parse()
+                    .map_err(|_| parse_error(format!("invalid element count `{count}`")))?,
+                properties: Vec::new(),
+            }),
+            ["property", ..] => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| parse_error("property declared before any element"))?;
+                element.properties.push(match tokens[1..] {
+                    ["list", count_type, data_type, name] => Property {
+                        name: name.to_owned(),
+                        data_type: ScalarType::parse(data_type)?,
+                        count_type: Some(ScalarType::parse(count_type)?),
+                    },
+                    [data_type, name] => Property {
+                        name: name.to_owned(),
+                        data_type: ScalarType::parse(data_type)?,
+                        count_type: None,
+                    },
+                    _ => return Err(parse_error(format!("invalid property `{line}`"))),
+                });
+            }
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(parse_error(format!("invalid header line `{line}`"))),
+        }
+    }
+    let format = format.ok_or_else(|| parse_error("missing `format`"))?;
+    Ok((Header { format, elements }, &data[body_start..]))
+}
+
+struct BodyReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    format: Format,
+}
+
+impl BodyReader<'_> {
+    fn read(&mut self, data_type: ScalarType) -> Result<f64, PlyError> {
+        if self.format == Format::Ascii {
+            return self.read_ascii();
+        }
+        let size = data_type.size();
+        let bytes = self
+            .data
+            .get(self.position..self.position + size)
+            .ok_or_else(|| parse_error("unexpected end of file"))?;
+        self.position += size;
+        let mut buffer = [0; 8];
+        buffer[..size].copy_from_slice(bytes);
+        if self.format == Format::BinaryBigEndian {
+            buffer[..size].reverse();
+        }
+        let [b0, b1, b2, b3, ..] = buffer;
+        Ok(match data_type {
+            ScalarType::I8 => b0 as i8 as f64,
+            ScalarType::U8 => b0 as f64,
+            ScalarType::I16 => i16::from_le_bytes([b0, b1]) as f64,
+            ScalarType::U16 => u16::from_le_bytes([b0, b1]) as f64,
+            ScalarType::I32 => i32::from_le_bytes([b0, b1, b2, b3]) as f64,
+            ScalarType::U32 => u32::from_le_bytes([b0, b1, b2, b3]) as f64,
+            ScalarType::F32 => f32::from_le_bytes([b0, b1, b2, b3]) as f64,
+            ScalarType::F64 => f64::from_le_bytes(buffer),
+        })
+    }
+
+    fn read_ascii(&mut self) -> Result<f64, PlyError> {
+        let rest = &self.data[self.position..];
+        let start = rest
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .ok_or_else(|| parse_error("unexpected end of file"))?;
+        let length = rest[start..]
+            .iter()
+            .position(|b| b.is_ascii_whitespace())
+            .unwrap_or(rest.len() - start);
+        self.position += start + length;
+        let token = std::str::from_utf8(&rest[start..start + length]).unwrap_or_default();
+        token
+            .parse()
+            .map_err(|_| parse_error(format!("invalid number `{token}`")))
+    }
+
+    /// Reads a scalar property, or every item of a list property.
+    fn read_property(&mut self, property: &Property) -> Result<Vec<f64>, PlyError> {
+        match property.count_type {
+            Some(count_type) => {
+                let count = self.read(count_type)? as usize;
+                (0..count).map(|_| self.read(property.data_type)).collect()
+            }
+            None => Ok(vec![self.read(property.data_type)?]),
+        }
+    }
+}
+
+fn read_vertex(reader: &mut BodyReader, properties: &[Property]) -> Result<Vertex, PlyError> {
+    let mut position = Vec3::zeros();
+    let mut normal = Vec3::zeros();
+    let mut color = WHITE;
+    let mut uv = vec2(0.0, 0.0);
+    for property in properties {
+        let value = reader.read_property(property)?;
+        let value = value.first().copied().unwrap_or_default();
+        let color_channel = (value / property.data_type.max()) as f32;
+        match property.name.as_str() {
+            "x" => position.x = value as f32,
+            "y" => position.y = value as f32,
+            "z" => position.z = value as f32,
+            "nx" => normal.x = value as f32,
+            "ny" => normal.y = value as f32,
+            "nz" => normal.z = value as f32,
+            "red" | "r" => color.x = color_channel,
+            "green" | "g" => color.y = color_channel,
+            "blue" | "b" => color.z = color_channel,
+            "alpha" | "a" => color.w = color_channel,
+            "u" | "s" | "texture_u" => uv.x = value as f32,
+            "v" | "t" | "texture_v" => uv.y = value as f32,
+            _ => {}
+        }
+    }
+    Ok(Vertex::new(position, color, uv).with_normal(normal))
+}
+
+fn read_face(
+    reader: &mut BodyReader,
+    properties: &[Property],
+    indices: &mut Vec<u32>,
+) -> Result<(), PlyError> {
+    for property in properties {
+        let values = reader.read_property(property)?;
+        if matches!(property.name.as_str(), "vertex_indices" | "vertex_index") {
+            let polygon = values
+                .iter()
+                .map(|&i| {
+                    if i >= 0.0 && i <= u32::MAX as f64 && i.fract() == 0.0 {
+                        Ok(i as u32)
+                    } else {
+                        Err(parse_error(format!("invalid vertex index `{i}`")))
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            indices.extend(fan_triangulate(&polygon));
+        }
+    }
+    Ok(())
+}
+
+fn parse_error(message: impl Into<String>) -> PlyError {
+    PlyError::Parse(message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{Color, GREEN, RED};
+    use nalgebra_glm::vec3;
+
+    #[test]
+    fn ascii_with_colors_and_polygons() {
+        let data = b"ply
+format ascii 1.0
+comment scanned
+comment written before end_header
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 0
+1 0 0 0 255 0
+1 1 0 0 0 255
+0 1 0 255 255 255
+4 0 1 2 3
+";
+
+        let (vertices, indices) = parse_ply(data).unwrap();
+
+        assert_eq!(vertices.len(), 4);
+        assert_eq!(vertices[0].color, RED);
+        assert_eq!(vertices[1].color, GREEN);
+        assert_eq!(vertices[2].coords, vec3(1.0, 1.0, 0.0).push(1.0));
+        assert_eq!(indices, [0, 1, 2, 0, 2, 3]);
+    }
+
+    #[test]
+    fn binary_big_endian_with_skipped_elements() {
+        let mut data = b"ply
+format binary_big_endian 1.0
+element vertex 3
+property float x
+property float y
+property float z
+property float red
+property float green
+property float blue
+element edge 1
+property int vertex1
+property int vertex2
+element face 1
+property list uchar uint vertex_indices
+end_header
+"
+        .to_vec();
+        for v in [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]] {
+            for c in v.into_iter().chain([0.25, 0.5, 0.75]) {
+                data.extend(c.to_be_bytes());
+            }
+        }
+        data.extend(0i32.to_be_bytes());
+        data.extend(1i32.to_be_bytes());
+        data.push(3);
+        for i in [0u32, 1, 2] {
+            data.extend(i.to_be_bytes());
+        }
+
+        let (vertices, indices) = parse_ply(&data).unwrap();
+
+        assert_eq!(vertices[1].coords, vec3(1.0, 0.0, 0.0).push(1.0));
+        assert_eq!(vertices[2].color, Color::new(0.25, 0.5, 0.75, 1.0));
+        assert_eq!(indices, [0, 1, 2]);
+    }
+
+    #[test]
+    fn malformed_files_are_rejected() {
+        let error = |data: &[u8]| parse_ply(data).err().unwrap().to_string();
+
+        assert_eq!(
+            error(b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\n"),
+            "invalid PLY file: missing `end_header`"
+        );
+        assert_eq!(
+            error(b"ply\nformat ascii 1.0\nelement vertex 2\nproperty float x\nend_header\n1\n"),
+            "invalid PLY file: unexpected end of file"
+        );
+        assert_eq!(
+            error(
+                b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\n\
+                  element face 1\nproperty list uchar int vertex_indices\nend_header\n0\n3 0 1 2\n"
+            ),
+            "invalid PLY file: face refers to vertex 1, but there are 1"
+        );
+        assert_eq!(
+            error(
+                b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\n\
+                  element face 1\nproperty list uchar int vertex_indices\nend_header\n0\n3 0 -1 0\n"
+            ),
+            "invalid PLY file: invalid vertex index `-1`"
+        );
+    }
+}
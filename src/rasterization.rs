@@ -1,90 +1,360 @@
-use nalgebra_glm::{ceil, floor, vec2, vec3, RealNumber, TVec2, Vec2, Vec3};
+use nalgebra_glm::{ceil, floor, vec2, vec3, TVec2, Vec2, Vec3};
 use simba::scalar::FixedI28F4;
 
-type FVec2 = TVec2<FixedI28F4>;
+use crate::{multisample::SampleCount, viewport::Rect};
 
-const EPSILON: FixedI28F4 = FixedI28F4::from_bits(0x01);
+const SUBPIXEL_BITS: u32 = 4;
+const SUBPIXELS: i64 = 1 << SUBPIXEL_BITS;
+
+/// Number of pixels whose coverage is evaluated together.
+const LANES: usize = 8;
+/// Side of the square blocks the bounding box of a triangle is traversed in. A block row is
+/// evaluated in a single pass.
+const BLOCK_SIZE: usize = LANES;
+const MAX_SAMPLES: usize = 8;
 
 #[derive(Debug, Clone, Copy)]
 pub struct Fragment {
     pub coords: TVec2<usize>,
+    /// Bit `i` is set when sample `i` of the pixel is covered. Lines and points cover every
+    /// sample.
+    pub coverage: u32,
+    /// Barycentric coordinates at the pixel center.
     pub t: Vec3,
     pub dt_dx: Vec3,
     pub dt_dy: Vec3,
 }
 
-pub fn rasterize_solid_triangle(vertices: &[Vec2; 3], mut f: impl FnMut(Fragment)) {
-    let [c0, c1, c2] = vertices.clone().map(vec2_to_fvec2);
+/// Signed area of the parallelogram spanned by a triangle, after snapping its vertices to the
+/// rasterizer's grid. Positive for triangles that wind counterclockwise on the framebuffer.
+pub fn signed_area(vertices: &[Vec2; 3]) -> f32 {
+    let [c0, c1, c2] = (*vertices).map(snap);
+    edge_function(c0, c1, c2) as f32 / (SUBPIXELS * SUBPIXELS) as f32
+}
+
+/// Rasterizes a triangle of either winding, testing coverage at pixel centers. Degenerate
+/// triangles produce no fragments.
+pub fn rasterize_solid_triangle(vertices: &[Vec2; 3], bounds: &Rect, f: impl FnMut(Fragment)) {
+    rasterize_multisampled_triangle(vertices, bounds, SampleCount::X1, f);
+}
+
+/// Rasterizes a triangle of either winding, testing coverage at each of the standard sample
+/// positions. A fragment is emitted for every pixel with at least one covered sample.
+pub fn rasterize_multisampled_triangle(
+    vertices: &[Vec2; 3],
+    bounds: &Rect,
+    samples: SampleCount,
+    mut f: impl FnMut(Fragment),
+) {
+    if signed_area(vertices) < 0.0 {
+        let [v0, v1, v2] = *vertices;
+        rasterize_counterclockwise_triangle(&[v0, v2, v1], bounds, samples, |fragment| {
+            f(Fragment {
+                t: fragment.t.xzy(),
+                dt_dx: fragment.dt_dx.xzy(),
+                dt_dy: fragment.dt_dy.xzy(),
+                ..fragment
+            })
+        });
+    } else {
+        rasterize_counterclockwise_triangle(vertices, bounds, samples, f);
+    }
+}
 
-    let min = floor(&c0.inf(&c1.inf(&c2)));
-    let max = ceil(&c0.sup(&c1.sup(&c2)));
+/// Walks the bounding box in blocks, skipping those outside the triangle and filling those inside
+/// without testing each pixel. Other blocks evaluate the three edge functions in integer fixed
+/// point for a whole row at once.
+fn rasterize_counterclockwise_triangle(
+    vertices: &[Vec2; 3],
+    bounds: &Rect,
+    samples: SampleCount,
+    mut f: impl FnMut(Fragment),
+) {
+    if bounds.is_empty() {
+        return;
+    }
+    let [c0, c1, c2] = (*vertices).map(snap);
 
-    let signed_area = edge_function(c0, c1, c2).0.to_num::<f32>();
+    let signed_area = edge_function(c0, c1, c2);
+    if signed_area <= 0 {
+        return;
+    }
 
-    if signed_area < 0.0 {
+    let min_x = (c0.x.min(c1.x).min(c2.x) >> SUBPIXEL_BITS).max(bounds.x as i64);
+    let min_y = (c0.y.min(c1.y).min(c2.y) >> SUBPIXEL_BITS).max(bounds.y as i64);
+    let max_x = ((c0.x.max(c1.x).max(c2.x) + SUBPIXELS - 1) >> SUBPIXEL_BITS)
+        .min((bounds.x + bounds.width) as i64 - 1);
+    let max_y = ((c0.y.max(c1.y).max(c2.y) + SUBPIXELS - 1) >> SUBPIXEL_BITS)
+        .min((bounds.y + bounds.height) as i64 - 1);
+    if min_x > max_x || min_y > max_y {
         return;
     }
 
-    let half = FixedI28F4::from_bits(0b1000);
-    let offset = vec2(half, half);
+    let edges = [(c1, c2), (c2, c0), (c0, c1)];
+    let w_bias = edges.map(|(start, end)| left_or_top_edge_bias(start, end));
+    let origin = vec2(min_x, min_y) * SUBPIXELS + vec2(SUBPIXELS / 2, SUBPIXELS / 2);
+    let w_0: [i64; 3] = std::array::from_fn(|i| {
+        let (start, end) = edges[i];
+        edge_function(start, end, origin) + w_bias[i]
+    });
+    // Offset of the edge functions at each sample, relative to the pixel center.
+    let positions = samples.positions();
+    let sample_dw: [[i64; 3]; MAX_SAMPLES] = std::array::from_fn(|sample| {
+        let [x, y] = positions.get(sample).copied().unwrap_or_default();
+        edges.map(|(start, end)| x as i64 * (end.y - start.y) - y as i64 * (end.x - start.x))
+    });
+    let sample_dw = &sample_dw[..positions.len()];
+    let sample_range: [(i64, i64); 3] = std::array::from_fn(|i| {
+        sample_dw
+            .iter()
+            .fold((i64::MAX, i64::MIN), |(min, max), dw| {
+                (min.min(dw[i]), max.max(dw[i]))
+            })
+    });
+    let dw_dx = edges.map(|(start, end)| (end.y - start.y) * SUBPIXELS);
+    let dw_dy = edges.map(|(start, end)| (start.x - end.x) * SUBPIXELS);
+
+    let area_inv = 1.0 / signed_area as f32;
+    let to_barycentric = |w: [i64; 3]| vec3(w[0] as f32, w[1] as f32, w[2] as f32) * area_inv;
+    let dt_dx = to_barycentric(dw_dx);
+    let dt_dy = to_barycentric(dw_dy);
+
+    let block_size = BLOCK_SIZE as i64;
+    for block_y in (min_y..=max_y).step_by(BLOCK_SIZE) {
+        for block_x in (min_x..=max_x).step_by(BLOCK_SIZE) {
+            let w_block: [i64; 3] = std::array::from_fn(|i| {
+                w_0[i] + (block_x - min_x) * dw_dx[i] + (block_y - min_y) * dw_dy[i]
+            });
+            let coverage = classify_block(w_block, dw_dx, dw_dy, sample_range);
+            if coverage == BlockCoverage::Outside {
+                continue;
+            }
+            let columns = (max_x - block_x + 1).min(block_size) as u32;
+            let column_mask = u32::MAX >> (32 - columns);
+            let mut w_row = w_block;
+            for y in block_y..=(block_y + block_size - 1).min(max_y) {
+                let mut sample_masks = [column_mask; MAX_SAMPLES];
+                if coverage == BlockCoverage::Partial {
+                    for (mask, dw) in sample_masks.iter_mut().zip(sample_dw) {
+                        let w_sample = std::array::from_fn(|i| w_row[i] + dw[i]);
+                        *mask &= coverage_mask(w_sample, dw_dx);
+                    }
+                }
+                let sample_masks = &sample_masks[..sample_dw.len()];
+                let mut mask = sample_masks.iter().fold(0, |mask, m| mask | m);
+                while mask != 0 {
+                    let lane = mask.trailing_zeros();
+                    let coverage = sample_masks
+                        .iter()
+                        .enumerate()
+                        .fold(0, |coverage, (sample, m)| {
+                            coverage | ((m >> lane) & 1) << sample
+                        });
+                    let lane = lane as i64;
+                    let w: [i64; 3] =
+                        std::array::from_fn(|i| w_row[i] - w_bias[i] + lane * dw_dx[i]);
+                    f(Fragment {
+                        coords: vec2((block_x + lane) as usize, y as usize),
+                        coverage,
+                        t: to_barycentric(w),
+                        dt_dx,
+                        dt_dy,
+                    });
+                    mask &= mask - 1;
+                }
+                w_row = std::array::from_fn(|i| w_row[i] + dw_dy[i]);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BlockCoverage {
+    Outside,
+    Inside,
+    Partial,
+}
 
-    let w_bias = vec3(
-        left_or_top_edge_bias(c1, c2, EPSILON),
-        left_or_top_edge_bias(c2, c0, EPSILON),
-        left_or_top_edge_bias(c0, c1, EPSILON),
-    );
-    let w_0 = vec3(
-        edge_function(c1, c2, min + offset),
-        edge_function(c2, c0, min + offset),
-        edge_function(c0, c1, min + offset),
-    ) + w_bias;
-    let dw_dx = vec3(c2.y - c1.y, c0.y - c2.y, c1.y - c0.y);
-    let dw_dy = vec3(c1.x - c2.x, c2.x - c0.x, c0.x - c1.x);
+/// Classifies a block of `BLOCK_SIZE`² pixels from the edge function values `w` at its first
+/// pixel center and the range of the sample offsets of each edge. Edge functions are linear, so
+/// their extremes over the block are at its corners.
+#[inline]
+fn classify_block(
+    w: [i64; 3],
+    dw_dx: [i64; 3],
+    dw_dy: [i64; 3],
+    sample_range: [(i64, i64); 3],
+) -> BlockCoverage {
+    let extent = BLOCK_SIZE as i64 - 1;
+    let mut coverage = BlockCoverage::Inside;
+    for i in 0..3 {
+        let (dx, dy) = (dw_dx[i] * extent, dw_dy[i] * extent);
+        let (sample_min, sample_max) = sample_range[i];
+        if w[i] + dx.max(0) + dy.max(0) + sample_max < 0 {
+            return BlockCoverage::Outside;
+        }
+        if w[i] + dx.min(0) + dy.min(0) + sample_min < 0 {
+            coverage = BlockCoverage::Partial;
+        }
+    }
+    coverage
+}
 
-    let dw_dx_f32 = dw_dx.map(|c| c.0.to_num()) / signed_area;
-    let dw_dy_f32 = dw_dy.map(|c| c.0.to_num()) / signed_area;
+/// Evaluates the edge functions for `LANES` consecutive pixels, starting with the values `w`,
+/// and returns a mask of the pixels inside all three edges.
+#[inline]
+fn coverage_mask(w: [i64; 3], dw_dx: [i64; 3]) -> u32 {
+    let lanes: [[i64; LANES]; 3] =
+        std::array::from_fn(|i| std::array::from_fn(|lane| w[i] + lane as i64 * dw_dx[i]));
+    let [w0, w1, w2] = lanes;
+    // A pixel is inside when no edge function is negative, i.e. no sign bit is set.
+    (0..LANES).fold(0, |mask, lane| {
+        let inside = (w0[lane] | w1[lane] | w2[lane]) >= 0;
+        mask | (inside as u32) << lane
+    })
+}
 
-    let mut w_y = w_0;
+/// Rasterizes a line following the diamond-exit rule: a pixel is covered when the line exits the
+/// diamond inscribed in it, so the last pixel of a line is left out and connected lines do not
+/// overlap.
+pub fn rasterize_line(vertices: &[Vec2; 2], bounds: &Rect, mut f: impl FnMut(Fragment)) {
+    let [p0, p1] = *vertices;
+    let d = p1 - p0;
+    let length_squared = d.norm_squared();
+    if length_squared == 0.0 {
+        return;
+    }
+    let dt_dx = vec3(-d.x, d.x, 0.0) / length_squared;
+    let dt_dy = vec3(-d.y, d.y, 0.0) / length_squared;
+    let mut emit = |pixel: Vec2| {
+        if pixel.x < 0.0 || pixel.y < 0.0 || inside_diamond(pixel, p1) {
+            return;
+        }
+        let coords = pixel.map(|c| c as usize);
+        if !bounds.contains((coords.x, coords.y)) {
+            return;
+        }
+        let s = (pixel + vec2(0.5, 0.5) - p0).dot(&d) / length_squared;
+        f(Fragment {
+            coords,
+            coverage: u32::MAX,
+            t: vec3(1.0 - s, s, 0.0),
+            dt_dx,
+            dt_dy,
+        });
+    };
+
+    let x_major = d.x.abs() >= d.y.abs();
+    let (major, minor) = if x_major { (0, 1) } else { (1, 0) };
+    let start_pixel = floor(&p0);
+    let start_center = start_pixel[major] + 0.5;
+    let reaches_start_center = (start_center - p0[major]) * d[major] >= 0.0;
+    if inside_diamond(start_pixel, p0) && !reaches_start_center {
+        emit(start_pixel);
+    }
+
+    // Only walk the part of the line within a pixel of the bounds, which may be a single tile.
+    let bounds_min = vec2(bounds.x, bounds.y).cast::<f32>().add_scalar(-1.0);
+    let bounds_max = (vec2(bounds.x, bounds.y) + vec2(bounds.width, bounds.height))
+        .cast::<f32>()
+        .add_scalar(1.0);
+    let (mut s_min, mut s_max) = (0.0f32, 1.0f32);
+    for axis in 0..2 {
+        if d[axis] == 0.0 {
+            if p0[axis] < bounds_min[axis] || p0[axis] > bounds_max[axis] {
+                return;
+            }
+            continue;
+        }
+        let s0 = (bounds_min[axis] - p0[axis]) / d[axis];
+        let s1 = (bounds_max[axis] - p0[axis]) / d[axis];
+        s_min = s_min.max(s0.min(s1));
+        s_max = s_max.min(s0.max(s1));
+    }
+    if s_min > s_max {
+        return;
+    }
+
+    let step = d[major].signum();
+    let end = if s_max < 1.0 {
+        (p0[major] + s_max * d[major]).floor()
+    } else {
+        p1[major].floor()
+    };
+    let mut i = if s_min > 0.0 {
+        (p0[major] + s_min * d[major]).floor()
+    } else {
+        start_pixel[major]
+    };
+    loop {
+        let center = i + 0.5;
+        let s = (center - p0[major]) / d[major];
+        if (0.0..=1.0).contains(&s) {
+            let mut pixel = Vec2::zeros();
+            pixel[major] = i;
+            pixel[minor] = (p0[minor] + s * d[minor]).floor();
+            emit(pixel);
+        }
+        if i == end {
+            break;
+        }
+        i += step;
+    }
+}
+
+/// Rasterizes a square point of the given size, covering the pixels whose centers lie inside it.
+pub fn rasterize_point(center: Vec2, size: f32, bounds: &Rect, mut f: impl FnMut(Fragment)) {
+    let bounds_min = vec2(bounds.x, bounds.y).cast::<f32>();
+    let bounds_max = bounds_min + vec2(bounds.width, bounds.height).cast();
+    let min = ceil(&(center.add_scalar(-size / 2.0 - 0.5))).sup(&bounds_min);
+    let max = ceil(&(center.add_scalar(size / 2.0 - 0.5))).inf(&bounds_max);
     let mut y = min.y;
-    while y <= max.y {
-        let mut w = w_y;
+    while y < max.y {
         let mut x = min.x;
-        while x <= max.x {
-            if w.x >= num::zero() && w.y >= num::zero() && w.z >= num::zero() {
-                f(Fragment {
-                    coords: vec2(x, y).map(|c| c.0.to_num()),
-                    t: w.map(|c| c.0.to_num::<f32>()) / signed_area,
-                    dt_dx: dw_dx_f32,
-                    dt_dy: dw_dy_f32,
-                })
-            }
-            w += dw_dx;
-            x += num::one();
+        while x < max.x {
+            f(Fragment {
+                coords: vec2(x as usize, y as usize),
+                coverage: u32::MAX,
+                t: vec3(1.0, 0.0, 0.0),
+                dt_dx: Vec3::zeros(),
+                dt_dy: Vec3::zeros(),
+            });
+            x += 1.0;
         }
-        w_y += dw_dy;
-        y += num::one();
+        y += 1.0;
     }
 }
 
 #[inline]
-fn vec2_to_fvec2(src: Vec2) -> FVec2 {
-    src.map(|c| FixedI28F4::from_num(c))
+fn inside_diamond(pixel: Vec2, point: Vec2) -> bool {
+    let distance = point - pixel - vec2(0.5, 0.5);
+    distance.x.abs() + distance.y.abs() < 0.5
+}
+
+/// Snaps a point to the sub-pixel grid, in units of `1 / SUBPIXELS` pixels.
+#[inline]
+fn snap(src: Vec2) -> TVec2<i64> {
+    src.map(|c| FixedI28F4::from_num(c).to_bits() as i64)
 }
 
+/// Edge function of the point `p` against the edge from `v0` to `v1`, in units of
+/// `1 / SUBPIXELS^2` pixels.
 #[inline]
-fn edge_function<T: RealNumber>(v0: TVec2<T>, v1: TVec2<T>, v2: TVec2<T>) -> T {
-    TVec2::perp(&(v2 - v0), &(v1 - v0))
+fn edge_function(v0: TVec2<i64>, v1: TVec2<i64>, p: TVec2<i64>) -> i64 {
+    (p.x - v0.x) * (v1.y - v0.y) - (p.y - v0.y) * (v1.x - v0.x)
 }
 
+/// Pixels on an edge belong to the triangle only if it is a left or a top edge; the bias excludes
+/// them otherwise.
 #[inline]
-fn left_or_top_edge_bias<T: RealNumber>(start: TVec2<T>, end: TVec2<T>, epsilon: T) -> T {
+fn left_or_top_edge_bias(start: TVec2<i64>, end: TVec2<i64>) -> i64 {
     let edge = end - start;
-    let is_left_edge = edge.y > num::zero();
-    let is_top_edge = edge.y == num::zero() && edge.x < num::zero();
+    let is_left_edge = edge.y > 0;
+    let is_top_edge = edge.y == 0 && edge.x < 0;
     if is_left_edge || is_top_edge {
-        return num::zero();
+        0
     } else {
-        return -epsilon;
+        -1
     }
 }
 
@@ -92,12 +362,15 @@ fn left_or_top_edge_bias<T: RealNumber>(start: TVec2<T>, end: TVec2<T>, epsilon:
 mod test {
     use super::*;
 
+    const BOUNDS: Rect = Rect::new(0, 0, 16, 16);
+
     #[test]
     pub fn half_pixel_center() {
         let mut fragments = Vec::new();
 
         rasterize_solid_triangle(
             &[vec2(1.25, 1.25), vec2(1.5, 1.75), vec2(1.75, 1.25)],
+            &BOUNDS,
             |Fragment { coords, .. }| fragments.push(coords),
         );
 
@@ -110,9 +383,207 @@ mod test {
 
         rasterize_solid_triangle(
             &[vec2(0.5, 0.5), vec2(2.5, 2.5), vec2(2.5, 0.5)],
+            &BOUNDS,
             |Fragment { coords, .. }| fragments.push(coords),
         );
 
         assert_eq!(fragments, [vec2(0, 0), vec2(1, 0), vec2(1, 1)]);
     }
+
+    #[test]
+    pub fn line_diamond_exit() {
+        let mut fragments = Vec::new();
+
+        rasterize_line(
+            &[vec2(0.5, 0.5), vec2(3.5, 0.5)],
+            &BOUNDS,
+            |Fragment { coords, .. }| fragments.push(coords),
+        );
+
+        assert_eq!(fragments, [vec2(0, 0), vec2(1, 0), vec2(2, 0)]);
+    }
+
+    #[test]
+    pub fn line_starting_past_the_center() {
+        let mut fragments = Vec::new();
+
+        rasterize_line(
+            &[vec2(1.5, 2.75), vec2(1.5, 4.25)],
+            &BOUNDS,
+            |Fragment { coords, .. }| fragments.push(coords),
+        );
+
+        assert_eq!(fragments, [vec2(1, 2), vec2(1, 3)]);
+    }
+
+    #[test]
+    pub fn lines_are_clipped_to_the_bounds() {
+        let bounds = Rect::new(2, 1, 3, 2);
+        let lines = [
+            [vec2(0.5, 0.5), vec2(7.5, 3.25)],
+            [vec2(6.25, 0.75), vec2(0.5, 2.5)],
+            [vec2(3.5, -4.0), vec2(2.25, 7.5)],
+            [vec2(-1e7, 1.5), vec2(1e7, 1.5)],
+            [vec2(0.5, 5.5), vec2(7.5, 5.5)],
+        ];
+
+        for line in lines {
+            let mut clipped = Vec::new();
+            rasterize_line(&line, &bounds, |Fragment { coords, .. }| {
+                clipped.push(coords)
+            });
+            let mut expected = Vec::new();
+            rasterize_line(
+                &line,
+                &Rect::new(0, 0, 8, 8),
+                |Fragment { coords, .. }| {
+                    if bounds.contains((coords.x, coords.y)) {
+                        expected.push(coords)
+                    }
+                },
+            );
+
+            assert_eq!(clipped, expected, "{line:?}");
+        }
+    }
+
+    #[test]
+    pub fn line_interpolation() {
+        let mut ts = Vec::new();
+
+        rasterize_line(
+            &[vec2(0.5, 0.5), vec2(2.5, 0.5)],
+            &BOUNDS,
+            |Fragment { t, .. }| ts.push(t),
+        );
+
+        assert_eq!(ts, [vec3(1.0, 0.0, 0.0), vec3(0.5, 0.5, 0.0)]);
+    }
+
+    #[test]
+    pub fn point_covers_pixel_centers() {
+        let mut fragments = Vec::new();
+
+        rasterize_point(
+            vec2(2.0, 2.0),
+            2.0,
+            &BOUNDS,
+            |Fragment { coords, .. }| fragments.push(coords),
+        );
+
+        assert_eq!(fragments, [vec2(1, 1), vec2(2, 1), vec2(1, 2), vec2(2, 2)]);
+    }
+
+    #[test]
+    pub fn clockwise_triangles_cover_the_same_pixels() {
+        let vertices = [vec2(0.5, 0.5), vec2(2.5, 2.5), vec2(2.5, 0.5)];
+        let [v0, v1, v2] = vertices;
+        let mut counterclockwise = Vec::new();
+        let mut clockwise = Vec::new();
+
+        rasterize_solid_triangle(&vertices, &BOUNDS, |fragment| {
+            counterclockwise.push(fragment)
+        });
+        rasterize_solid_triangle(&[v0, v2, v1], &BOUNDS, |fragment| clockwise.push(fragment));
+
+        assert!(signed_area(&vertices) > 0.0);
+        assert!(signed_area(&[v0, v2, v1]) < 0.0);
+        assert_eq!(
+            clockwise
+                .iter()
+                .map(|f| (f.coords, f.t.xzy()))
+                .collect::<Vec<_>>(),
+            counterclockwise
+                .iter()
+                .map(|f| (f.coords, f.t))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    pub fn bounds_clip_fragments() {
+        let mut fragments = Vec::new();
+
+        rasterize_solid_triangle(
+            &[vec2(0.0, 0.0), vec2(0.0, 8.0), vec2(8.0, 0.0)],
+            &Rect::new(1, 0, 2, 2),
+            |Fragment { coords, .. }| fragments.push(coords),
+        );
+
+        assert_eq!(fragments, [vec2(1, 0), vec2(2, 0), vec2(1, 1), vec2(2, 1)]);
+    }
+
+    #[test]
+    pub fn blocks_match_per_pixel_coverage() {
+        let bounds = Rect::new(0, 0, 48, 48);
+        let triangles = [
+            [vec2(0.3, 1.1), vec2(13.7, 11.4), vec2(10.2, 0.6)],
+            [vec2(1.0, 2.0), vec2(3.5, 47.5), vec2(45.25, 20.0)],
+            [vec2(0.0, 0.0), vec2(47.9, 44.1), vec2(47.0, 41.8)],
+        ];
+
+        for vertices in triangles {
+            let mut fragments = Vec::new();
+            rasterize_solid_triangle(&vertices, &bounds, |Fragment { coords, .. }| {
+                fragments.push(coords)
+            });
+
+            let [c0, c1, c2] = vertices.map(snap);
+            let inside = |x: i64, y: i64| {
+                let p = vec2(x, y) * SUBPIXELS + vec2(SUBPIXELS / 2, SUBPIXELS / 2);
+                [(c1, c2), (c2, c0), (c0, c1)].iter().all(|&(start, end)| {
+                    edge_function(start, end, p) + left_or_top_edge_bias(start, end) >= 0
+                })
+            };
+            let expected = (0..48)
+                .flat_map(|y| (0..48).map(move |x| (x, y)))
+                .filter(|&(x, y)| inside(x, y))
+                .map(|(x, y)| vec2(x as usize, y as usize))
+                .collect::<Vec<_>>();
+            fragments.sort_by_key(|c| (c.y, c.x));
+            assert!(expected.len() > LANES);
+            assert_eq!(fragments, expected);
+        }
+    }
+
+    #[test]
+    pub fn shared_edges_cover_each_sample_once() {
+        let quad = [
+            vec2(0.0, 0.0),
+            vec2(0.0, 8.0),
+            vec2(8.0, 8.0),
+            vec2(8.0, 0.0),
+        ];
+        for samples in [SampleCount::X2, SampleCount::X4, SampleCount::X8] {
+            let mut hits = [[0; 8]; 64];
+            for triangle in [[quad[0], quad[1], quad[2]], [quad[0], quad[2], quad[3]]] {
+                rasterize_multisampled_triangle(&triangle, &BOUNDS, samples, |fragment| {
+                    let pixel = fragment.coords.y * 8 + fragment.coords.x;
+                    for (sample, hit) in hits[pixel].iter_mut().enumerate() {
+                        *hit += (fragment.coverage >> sample) & 1;
+                    }
+                });
+            }
+
+            for pixel in hits {
+                assert_eq!(pixel[..samples.count()], vec![1; samples.count()]);
+            }
+        }
+    }
+
+    #[test]
+    pub fn partially_covered_pixels_report_their_samples() {
+        let mut fragments = Vec::new();
+
+        rasterize_multisampled_triangle(
+            &[vec2(0.0, 0.0), vec2(0.0, 4.0), vec2(1.5, 0.0)],
+            &BOUNDS,
+            SampleCount::X4,
+            |fragment| fragments.push((fragment.coords, fragment.coverage)),
+        );
+
+        // The edge crosses the second column, covering only samples left of x = 1.5 - 0.375 y.
+        assert!(fragments.contains(&(vec2(0, 0), 0b1111)));
+        assert!(fragments.contains(&(vec2(1, 0), 0b0101)));
+    }
 }
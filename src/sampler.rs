@@ -1,33 +1,87 @@
 use nalgebra_glm::{vec2, IVec2, Vec2};
 use num::Float;
 
-use crate::{color::Color, image::Image};
+use crate::{
+    color::{Color, TRANSPARENT},
+    image::Image,
+};
 
+/// How texel coordinates outside of the image are mapped back into it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AddressMode {
     Repeat,
+    /// Repeats the image, flipping every other copy.
+    MirroredRepeat,
+    /// Clamps to the edge texels.
     Clamp,
+    /// Mirrors the image once around the origin, then clamps to the edge texels.
+    MirrorClampToEdge,
+    /// Returns the sampler's border color outside of the image.
+    ClampToBorder,
 }
 
 impl AddressMode {
-    pub fn convert(&self, src: i32, size: usize) -> usize {
-        (match self {
-            AddressMode::Repeat => src % size as i32,
-            AddressMode::Clamp => src.clamp(0, (size - 1) as i32),
-        }) as usize
+    /// Maps a texel coordinate along an axis of `size` texels to an index into the image, or
+    /// `None` when the border color should be used instead.
+    pub fn convert(&self, src: i32, size: usize) -> Option<usize> {
+        let size = size as i32;
+        let mirror = |src: i32| if src >= 0 { src } else { -1 - src };
+        let index = match self {
+            AddressMode::Repeat => src.rem_euclid(size),
+            AddressMode::MirroredRepeat => {
+                let src = src.rem_euclid(2 * size);
+                if src < size {
+                    src
+                } else {
+                    2 * size - 1 - src
+                }
+            }
+            AddressMode::Clamp => src.clamp(0, size - 1),
+            AddressMode::MirrorClampToEdge => mirror(src).min(size - 1),
+            AddressMode::ClampToBorder if (0..size).contains(&src) => src,
+            AddressMode::ClampToBorder => return None,
+        };
+        Some(index as usize)
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Filter {
     Nearest,
     Linear,
+    /// Averages up to `2^l` linear probes spread along the major axis of the footprint, on the
+    /// mip level matching its minor axis.
     Anisotropic(i32),
+    /// Elliptical weighted average over the footprint, with its eccentricity limited to `2^l`.
+    /// Smoother than [`Filter::Anisotropic`], but touches every texel under the ellipse.
+    Ewa(i32),
+}
+
+/// Falloff of the Gaussian weights used by EWA filtering.
+const EWA_ALPHA: f32 = 2.0;
+
+/// How the sampler chooses between mip levels when minifying.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MipmapMode {
+    /// Samples the level closest to the computed level of detail.
+    #[default]
+    Nearest,
+    /// Blends the two levels around the computed level of detail.
+    Linear,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Sampler {
     u_address_mode: AddressMode,
     v_address_mode: AddressMode,
+    w_address_mode: AddressMode,
+    border_color: Color,
     min_filter: Filter,
     mag_filter: Filter,
+    mipmap_mode: MipmapMode,
+    lod_bias: f32,
+    min_lod: f32,
+    max_lod: f32,
 }
 
 impl Sampler {
@@ -40,79 +94,512 @@ impl Sampler {
         Self {
             u_address_mode,
             v_address_mode,
+            w_address_mode: AddressMode::Clamp,
+            border_color: TRANSPARENT,
             min_filter,
             mag_filter,
+            mipmap_mode: MipmapMode::default(),
+            lod_bias: 0.0,
+            min_lod: 0.0,
+            max_lod: f32::INFINITY,
         }
     }
 
+    /// Address mode of the third axis, for volume textures.
+    pub fn with_w_address_mode(mut self, w_address_mode: AddressMode) -> Self {
+        self.w_address_mode = w_address_mode;
+        self
+    }
+
+    /// Color returned for texels outside of the image with [`AddressMode::ClampToBorder`].
+    pub fn with_border_color(mut self, border_color: Color) -> Self {
+        self.border_color = border_color;
+        self
+    }
+
+    pub fn with_mipmap_mode(mut self, mipmap_mode: MipmapMode) -> Self {
+        self.mipmap_mode = mipmap_mode;
+        self
+    }
+
+    /// Offset added to the level of detail computed from the derivatives. Positive values blur.
+    pub fn with_lod_bias(mut self, lod_bias: f32) -> Self {
+        self.lod_bias = lod_bias;
+        self
+    }
+
+    /// Clamps the level of detail to `min_lod..=max_lod`.
+    pub fn with_lod_clamp(mut self, min_lod: f32, max_lod: f32) -> Self {
+        assert!(min_lod <= max_lod);
+        self.min_lod = min_lod;
+        self.max_lod = max_lod;
+        self
+    }
+
+    /// Address modes of the U, V and W axes.
+    pub fn address_modes(&self) -> [AddressMode; 3] {
+        [
+            self.u_address_mode,
+            self.v_address_mode,
+            self.w_address_mode,
+        ]
+    }
+
+    pub fn border_color(&self) -> Color {
+        self.border_color
+    }
+
+    pub fn mipmap_mode(&self) -> MipmapMode {
+        self.mipmap_mode
+    }
+
     pub fn sample(&self, image: &Image, uv: Vec2, duv_dx: Vec2, duv_dy: Vec2) -> Color {
-        let image_scale = vec2(image.width(), image.height()).cast();
-        let rs = uv.component_mul(&image_scale);
-        let scale_factor = vec2(
-            duv_dx.component_mul(&image_scale).norm(),
-            duv_dy.component_mul(&image_scale).norm(),
-        );
-        if scale_factor.min() > 1.0 {
-            match self.min_filter {
-                Filter::Nearest => {
-                    return self.nearest_sample(image, rs);
+        // Non-finite derivatives, from degenerate projections, cover the whole texture instead.
+        let finite = |duv: Vec2| duv.map(|d| if d.is_finite() { d } else { 1.0 });
+        let (duv_dx, duv_dy) = (finite(duv_dx), finite(duv_dy));
+        let lod = self.lod(image, duv_dx, duv_dy);
+        if lod <= 0.0 {
+            let rs = uv.component_mul(&texel_scale(image));
+            return match self.mag_filter {
+                Filter::Nearest => self.nearest_sample(image, rs),
+                Filter::Linear | Filter::Anisotropic(_) | Filter::Ewa(_) => {
+                    self.linear_sample(image, rs)
                 }
-                Filter::Linear => {
-                    return self.linear_sample(image, rs);
-                }
-                Filter::Anisotropic(l) => {
-                    let scale_factor = scale_factor.inf(&(vec2(1.0, 1.0) * 2.0.powi(l)));
-                    let rs_min = rs - scale_factor / 2.0;
-                    let mut color = Color::new(0.0, 0.0, 0.0);
-                    let mut y = 0.0;
-                    let mut x = 0.0;
-                    while y < scale_factor.y {
-                        x = 0.0;
-                        while x < scale_factor.x {
-                            color += self.linear_sample(image, rs_min + vec2(x, y));
-                            x += 1.0;
-                        }
-                        y += 1.0;
-                    }
-                    return color / (x * y);
+            };
+        }
+
+        match self.min_filter {
+            Filter::Nearest => self.sample_mip_levels(image, lod, |level| {
+                self.nearest_sample(level, uv.component_mul(&texel_scale(level)))
+            }),
+            Filter::Linear => self.sample_mip_levels(image, lod, |level| {
+                self.linear_sample(level, uv.component_mul(&texel_scale(level)))
+            }),
+            Filter::Anisotropic(l) => self.anisotropic_sample(image, uv, duv_dx, duv_dy, l),
+            Filter::Ewa(l) => self.ewa_sample(image, uv, duv_dx, duv_dy, l),
+        }
+    }
+
+    /// Level of detail for a footprint, after applying the bias and clamps. Values at or below
+    /// zero select magnification.
+    pub fn lod(&self, image: &Image, duv_dx: Vec2, duv_dy: Vec2) -> f32 {
+        let scale = texel_scale(image);
+        let rho = duv_dx
+            .component_mul(&scale)
+            .norm()
+            .max(duv_dy.component_mul(&scale).norm());
+        self.adjust_lod(rho.log2())
+    }
+
+    fn adjust_lod(&self, lod: f32) -> f32 {
+        (lod + self.lod_bias).clamp(self.min_lod, self.max_lod)
+    }
+
+    /// Samples the mip level or levels selected by `lod` according to the mipmap mode.
+    fn sample_mip_levels(
+        &self,
+        image: &Image,
+        lod: f32,
+        sample: impl Fn(&Image) -> Color,
+    ) -> Color {
+        let lod = lod.clamp(0.0, (image.mip_level_count() - 1) as f32);
+        match self.mipmap_mode {
+            MipmapMode::Nearest => sample(image.mip_level((lod + 0.5).floor() as usize)),
+            MipmapMode::Linear => {
+                let level = lod.floor();
+                let t = lod - level;
+                let color = sample(image.mip_level(level as usize));
+                if t == 0.0 {
+                    return color;
                 }
+                color * (1.0 - t) + sample(image.mip_level(level as usize + 1)) * t
             }
+        }
+    }
+
+    fn anisotropic_sample(
+        &self,
+        image: &Image,
+        uv: Vec2,
+        duv_dx: Vec2,
+        duv_dy: Vec2,
+        max_anisotropy_log2: i32,
+    ) -> Color {
+        let scale = texel_scale(image);
+        let (length_x, length_y) = (
+            duv_dx.component_mul(&scale).norm(),
+            duv_dy.component_mul(&scale).norm(),
+        );
+        let (major_axis, major, minor) = if length_x >= length_y {
+            (duv_dx, length_x, length_y)
         } else {
-            match self.mag_filter {
-                Filter::Nearest => {
-                    return self.nearest_sample(image, rs);
-                }
-                Filter::Linear | Filter::Anisotropic(_) => {
-                    return self.linear_sample(image, rs);
+            (duv_dy, length_y, length_x)
+        };
+        let max_probes = 2.0.powi(max_anisotropy_log2.max(0));
+        let probes = (major / minor.max(f32::MIN_POSITIVE))
+            .ceil()
+            .clamp(1.0, max_probes);
+        let lod = self.adjust_lod((major / probes).log2());
+
+        let probes = probes as usize;
+        let color = (0..probes)
+            .map(|i| {
+                let offset = (i as f32 + 0.5) / probes as f32 - 0.5;
+                let uv = uv + major_axis * offset;
+                self.sample_mip_levels(image, lod, |level| {
+                    self.linear_sample(level, uv.component_mul(&texel_scale(level)))
+                })
+            })
+            .sum::<Color>();
+        color / probes as f32
+    }
+
+    fn ewa_sample(
+        &self,
+        image: &Image,
+        uv: Vec2,
+        duv_dx: Vec2,
+        duv_dy: Vec2,
+        max_anisotropy_log2: i32,
+    ) -> Color {
+        // Without mips every minified footprint would be filtered on the base level.
+        if image.mip_level_count() == 1 {
+            return self.anisotropic_sample(image, uv, duv_dx, duv_dy, max_anisotropy_log2);
+        }
+        let scale = texel_scale(image);
+        let (mut major, mut minor) = (duv_dx, duv_dy);
+        if major.component_mul(&scale).norm() < minor.component_mul(&scale).norm() {
+            std::mem::swap(&mut major, &mut minor);
+        }
+        let (major_length, minor_length) = (
+            major.component_mul(&scale).norm(),
+            minor.component_mul(&scale).norm(),
+        );
+        // Widen overly eccentric ellipses so the number of texels visited stays bounded.
+        let max_anisotropy = 2.0.powi(max_anisotropy_log2.max(0));
+        if minor_length * max_anisotropy < major_length {
+            if minor_length > 0.0 {
+                minor *= major_length / (minor_length * max_anisotropy);
+            } else {
+                minor = vec2(-major.y, major.x) / max_anisotropy;
+            }
+        }
+        let lod = self.adjust_lod(minor.component_mul(&scale).norm().log2());
+        self.sample_mip_levels(image, lod, |level| {
+            self.ewa_level_sample(level, uv, major, minor)
+        })
+    }
+
+    /// Gaussian-weighted average of the texels inside the ellipse spanned by two axes.
+    fn ewa_level_sample(&self, level: &Image, uv: Vec2, axis0: Vec2, axis1: Vec2) -> Color {
+        let scale = texel_scale(level);
+        let center = uv.component_mul(&scale) - vec2(0.5, 0.5);
+        let (axis0, axis1) = (axis0.component_mul(&scale), axis1.component_mul(&scale));
+
+        // Implicit ellipse a*s^2 + b*s*t + c*t^2 < 1, grown by a texel so it always covers one.
+        let mut a = axis0.y * axis0.y + axis1.y * axis1.y + 1.0;
+        let mut b = -2.0 * (axis0.x * axis0.y + axis1.x * axis1.y);
+        let mut c = axis0.x * axis0.x + axis1.x * axis1.x + 1.0;
+        let inverse_f = 1.0 / (a * c - b * b / 4.0);
+        a *= inverse_f;
+        b *= inverse_f;
+        c *= inverse_f;
+
+        let determinant = -b * b + 4.0 * a * c;
+        // Footprints larger than the level, or not finite at all when the LOD is clamped or the
+        // derivatives are infinite, still visit every texel at most about once.
+        let extent = vec2(
+            (4.0 * c / determinant).sqrt(),
+            (4.0 * a / determinant).sqrt(),
+        )
+        .zip_map(&(scale / 2.0), f32::min);
+        let min = nalgebra_glm::ceil(&(center - extent)).map(|x| x as i32);
+        let max = nalgebra_glm::floor(&(center + extent)).map(|x| x as i32);
+
+        let mut color = Color::zeros();
+        let mut total = 0.0;
+        for j in min.y..=max.y {
+            let t = j as f32 - center.y;
+            for i in min.x..=max.x {
+                let s = i as f32 - center.x;
+                let r2 = a * s * s + b * s * t + c * t * t;
+                if r2 < 1.0 {
+                    let weight = (-EWA_ALPHA * r2).exp() - (-EWA_ALPHA).exp();
+                    color += weight * self.sample_texel(level, IVec2::new(i, j));
+                    total += weight;
                 }
             }
         }
+        if total > 0.0 {
+            color / total
+        } else {
+            self.linear_sample(level, uv.component_mul(&scale))
+        }
     }
 
     fn nearest_sample(&self, image: &Image, rs: Vec2) -> Color {
         let ij = nalgebra_glm::floor(&rs).try_cast().unwrap();
-        return self.sample_texel(image, ij);
+        self.sample_texel(image, ij)
     }
 
     fn linear_sample(&self, image: &Image, rs: Vec2) -> Color {
         let rs = rs - vec2(0.5, 0.5);
-        let a = nalgebra_glm::fract(&rs);
-        let ij0 = nalgebra_glm::floor(&rs).try_cast().unwrap();
+        let floor = nalgebra_glm::floor(&rs);
+        // Not `fract`, which rounds toward zero and gives negative weights left of the origin.
+        let a = rs - floor;
+        let ij0 = floor.try_cast().unwrap();
         let samples = [
             (ij0 + vec2(0, 0), 1.0 - a.x, 1.0 - a.y),
             (ij0 + vec2(1, 0), a.x, 1.0 - a.y),
             (ij0 + vec2(0, 1), 1.0 - a.x, a.y),
             (ij0 + vec2(1, 1), a.x, a.y),
         ];
-        return samples
+        samples
             .map(|(ij, w_i, w_j)| w_i * w_j * self.sample_texel(image, ij))
             .into_iter()
-            .sum();
+            .sum()
     }
 
     fn sample_texel(&self, image: &Image, ij: IVec2) -> Color {
         let i = self.u_address_mode.convert(ij.x, image.width());
         let j = self.v_address_mode.convert(ij.y, image.height());
-        return image.get_color((i, j));
+        match (i, j) {
+            (Some(i), Some(j)) => image.get_color((i, j)),
+            _ => self.border_color,
+        }
+    }
+}
+
+fn texel_scale(image: &Image) -> Vec2 {
+    vec2(image.width(), image.height()).cast()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        color::{to_raw_color, BLACK, WHITE},
+        mipmap::MipmapFilter,
+    };
+
+    /// An 8x8 image whose levels are white, black, white and black from the base level down.
+    fn striped_levels() -> Image {
+        let levels = [BLACK, WHITE, BLACK]
+            .into_iter()
+            .enumerate()
+            .map(|(level, color)| {
+                let size = 4 >> level;
+                Image::from_buffer(vec![to_raw_color(color); size * size], size, size)
+            })
+            .collect();
+        Image::from_buffer(vec![to_raw_color(WHITE); 64], 8, 8).with_mip_levels(levels)
+    }
+
+    fn sampler() -> Sampler {
+        Sampler::new(
+            AddressMode::Clamp,
+            AddressMode::Clamp,
+            Filter::Linear,
+            Filter::Linear,
+        )
+    }
+
+    fn sample_at_lod(sampler: &Sampler, image: &Image, lod: f32) -> f32 {
+        let texel = 1.0 / image.width() as f32;
+        let derivative = vec2(texel * lod.exp2(), 0.0);
+        sampler
+            .sample(image, vec2(0.5, 0.5), derivative, derivative)
+            .x
+    }
+
+    #[test]
+    fn lod_follows_the_larger_derivative() {
+        let image = Image::from_buffer(vec![0; 64 * 32], 64, 32);
+        let sampler = sampler();
+
+        assert_eq!(
+            sampler.lod(&image, vec2(4.0 / 64.0, 0.0), vec2(0.0, 1.0 / 32.0)),
+            2.0
+        );
+        assert_eq!(
+            sampler.lod(&image, vec2(1.0 / 64.0, 0.0), vec2(0.0, 8.0 / 32.0)),
+            3.0
+        );
+        assert_eq!(
+            sampler.with_lod_bias(-1.0).with_lod_clamp(0.5, 1.5).lod(
+                &image,
+                vec2(8.0 / 64.0, 0.0),
+                Vec2::zeros()
+            ),
+            1.5
+        );
+    }
+
+    #[test]
+    fn mipmap_modes_select_or_blend_levels() {
+        let image = striped_levels();
+        let nearest = sampler();
+        let linear = sampler().with_mipmap_mode(MipmapMode::Linear);
+
+        assert_eq!(sample_at_lod(&nearest, &image, 1.0), 0.0);
+        assert_eq!(sample_at_lod(&nearest, &image, 1.7), 1.0);
+        assert_eq!(sample_at_lod(&nearest, &image, 10.0), 0.0);
+        assert!((sample_at_lod(&linear, &image, 1.25) - 0.25).abs() < 1e-5);
+        assert!((sample_at_lod(&linear, &image, 2.5) - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn lod_clamps_and_bias_shift_the_level() {
+        let image = striped_levels();
+
+        assert_eq!(
+            sample_at_lod(&sampler().with_lod_bias(1.0), &image, 1.0),
+            1.0
+        );
+        assert_eq!(
+            sample_at_lod(&sampler().with_lod_clamp(0.0, 1.0), &image, 3.0),
+            0.0
+        );
+        assert_eq!(
+            sample_at_lod(&sampler().with_lod_clamp(2.0, 3.0), &image, 0.0),
+            1.0
+        );
+    }
+
+    /// A 64x64 image of vertical black and white stripes, four texels wide.
+    fn vertical_stripes() -> Image {
+        let buffer = (0..64 * 64)
+            .map(|i| to_raw_color(if i % 64 / 4 % 2 == 0 { BLACK } else { WHITE }))
+            .collect();
+        Image::from_buffer(buffer, 64, 64).with_mipmaps(MipmapFilter::Box)
+    }
+
+    #[test]
+    fn anisotropic_filters_keep_footprints_along_the_stripes_sharp() {
+        let image = vertical_stripes();
+        // One texel wide across the stripes and sixteen along them, centered on a black stripe.
+        let uv = vec2(2.0 / 64.0, 0.5);
+        let (duv_dx, duv_dy) = (vec2(1.0 / 64.0, 0.0), vec2(0.0, 16.0 / 64.0));
+        let with_min_filter = |filter| {
+            Sampler::new(
+                AddressMode::Clamp,
+                AddressMode::Clamp,
+                filter,
+                Filter::Linear,
+            )
+            .with_mipmap_mode(MipmapMode::Linear)
+            .sample(&image, uv, duv_dx, duv_dy)
+            .x
+        };
+
+        assert!((with_min_filter(Filter::Linear) - 0.5).abs() < 0.01);
+        assert_eq!(with_min_filter(Filter::Anisotropic(4)), 0.0);
+        assert!(with_min_filter(Filter::Ewa(4)) < 0.01);
+        // Two probes each cover eight texels, on a level where the stripes have averaged out.
+        assert!((with_min_filter(Filter::Anisotropic(1)) - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn ewa_handles_unbounded_footprints() {
+        let ewa = Sampler::new(
+            AddressMode::Repeat,
+            AddressMode::Repeat,
+            Filter::Ewa(4),
+            Filter::Linear,
+        );
+        let infinite = vec2(f32::INFINITY, 0.0);
+        let huge = vec2(0.0, 1e6);
+
+        for image in [
+            vertical_stripes(),
+            Image::from_buffer(vec![0; 64 * 64], 64, 64),
+        ] {
+            for sampler in [ewa, ewa.with_lod_clamp(0.0, 1.0)] {
+                for (duv_dx, duv_dy) in [(infinite, huge), (huge, huge / 4.0)] {
+                    let color = sampler.sample(&image, vec2(0.3, 0.6), duv_dx, duv_dy);
+                    assert!(color.iter().all(|c| c.is_finite()), "{color:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn isotropic_footprints_take_a_single_probe() {
+        let image = vertical_stripes();
+        let linear = sampler().with_mipmap_mode(MipmapMode::Linear);
+        let anisotropic = Sampler::new(
+            AddressMode::Clamp,
+            AddressMode::Clamp,
+            Filter::Anisotropic(4),
+            Filter::Linear,
+        )
+        .with_mipmap_mode(MipmapMode::Linear);
+
+        for (uv, derivative) in [(vec2(0.3, 0.6), 3.0), (vec2(0.71, 0.2), 5.5)] {
+            let (duv_dx, duv_dy) = (vec2(derivative, 0.0) / 64.0, vec2(0.0, derivative) / 64.0);
+            assert_eq!(
+                anisotropic.sample(&image, uv, duv_dx, duv_dy),
+                linear.sample(&image, uv, duv_dx, duv_dy)
+            );
+        }
+    }
+
+    #[test]
+    fn address_modes_map_negative_coordinates() {
+        let convert = |mode: AddressMode| (-5..8).map(|i| mode.convert(i, 3)).collect::<Vec<_>>();
+        let some = |indices: &[usize]| indices.iter().copied().map(Some).collect::<Vec<_>>();
+
+        assert_eq!(
+            convert(AddressMode::Repeat),
+            some(&[1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1])
+        );
+        assert_eq!(
+            convert(AddressMode::MirroredRepeat),
+            some(&[1, 2, 2, 1, 0, 0, 1, 2, 2, 1, 0, 0, 1])
+        );
+        assert_eq!(
+            convert(AddressMode::Clamp),
+            some(&[0, 0, 0, 0, 0, 0, 1, 2, 2, 2, 2, 2, 2])
+        );
+        assert_eq!(
+            convert(AddressMode::MirrorClampToEdge),
+            some(&[2, 2, 2, 1, 0, 0, 1, 2, 2, 2, 2, 2, 2])
+        );
+        assert_eq!(
+            convert(AddressMode::ClampToBorder),
+            [
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(0),
+                Some(1),
+                Some(2),
+                None,
+                None,
+                None,
+                None,
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn clamp_to_border_blends_with_the_border_color() {
+        let image = Image::from_buffer(vec![to_raw_color(WHITE); 4], 2, 2);
+        let sampler = Sampler::new(
+            AddressMode::ClampToBorder,
+            AddressMode::Repeat,
+            Filter::Nearest,
+            Filter::Linear,
+        )
+        .with_border_color(BLACK);
+        let sample = |u: f32| sampler.sample(&image, vec2(u, -0.25), Vec2::zeros(), Vec2::zeros());
+
+        assert_eq!(sample(0.5), WHITE);
+        assert_eq!(sample(-0.25), BLACK);
+        assert_eq!(sample(0.0), Color::new(0.5, 0.5, 0.5, 1.0));
+        assert_eq!(sample(1.5), BLACK);
     }
 }
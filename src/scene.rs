@@ -0,0 +1,628 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    path::Path,
+};
+
+use gltf::{
+    camera::Projection as GltfProjection,
+    image::Format,
+    mesh::Mode,
+    texture::{MagFilter, MinFilter, WrappingMode},
+};
+use nalgebra_glm::{vec2, Mat4, Vec2, Vec3};
+
+use crate::{
+    color::{Color, ColorSpace, WHITE},
+    image::Image,
+    mipmap::MipmapFilter,
+    sampler::{AddressMode, Filter, MipmapMode, Sampler},
+    topology::PrimitiveTopology,
+    vertex::Vertex,
+};
+
+/// Meshes, materials, textures, cameras and the node hierarchy of a glTF 2.0 file.
+pub struct Scene {
+    pub meshes: Vec<Mesh>,
+    pub materials: Vec<Material>,
+    pub images: Vec<Image>,
+    pub textures: Vec<Texture>,
+    pub cameras: Vec<Camera>,
+    pub nodes: Vec<Node>,
+    /// Nodes at the root of the default scene.
+    pub roots: Vec<usize>,
+}
+
+pub struct Mesh {
+    pub name: String,
+    pub primitives: Vec<MeshPrimitive>,
+}
+
+pub struct MeshPrimitive {
+    pub topology: PrimitiveTopology,
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    /// Index into [`Scene::materials`].
+    pub material: Option<usize>,
+}
+
+pub struct Material {
+    pub name: String,
+    pub base_color: Color,
+    /// Index into [`Scene::textures`].
+    pub base_color_texture: Option<usize>,
+}
+
+pub struct Texture {
+    /// Index into [`Scene::images`].
+    pub image: usize,
+    pub sampler: Sampler,
+}
+
+pub struct Camera {
+    pub name: String,
+    pub projection: Projection,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective {
+        /// Width over height, or the aspect ratio of the viewport if `None`.
+        aspect_ratio: Option<f32>,
+        y_fov: f32,
+        z_near: f32,
+        /// Infinite projection if `None`.
+        z_far: Option<f32>,
+    },
+    Orthographic {
+        x_mag: f32,
+        y_mag: f32,
+        z_near: f32,
+        z_far: f32,
+    },
+}
+
+pub struct Node {
+    pub name: String,
+    /// Transform relative to the parent node.
+    pub transform: Mat4,
+    pub children: Vec<usize>,
+    /// Index into [`Scene::meshes`].
+    pub mesh: Option<usize>,
+    /// Index into [`Scene::cameras`].
+    pub camera: Option<usize>,
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Gltf(gltf::Error),
+    UnsupportedImageFormat {
+        image: usize,
+        format: Format,
+    },
+    MissingPositions {
+        mesh: usize,
+    },
+    IndexOutOfRange {
+        mesh: usize,
+        index: u32,
+    },
+    /// The node has several parents or is part of a cycle.
+    InvalidNodeHierarchy {
+        node: usize,
+    },
+}
+
+impl Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Gltf(error) => write!(f, "cannot import glTF: {error}"),
+            SceneError::UnsupportedImageFormat { image, format } => {
+                write!(f, "image {image} has unsupported format {format:?}")
+            }
+            SceneError::MissingPositions { mesh } => {
+                write!(f, "a primitive of mesh {mesh} has no positions")
+            }
+            SceneError::IndexOutOfRange { mesh, index } => {
+                write!(
+                    f,
+                    "a primitive of mesh {mesh} refers to missing vertex {index}"
+                )
+            }
+            SceneError::InvalidNodeHierarchy { node } => {
+                write!(f, "node {node} has several parents or is part of a cycle")
+            }
+        }
+    }
+}
+
+impl Error for SceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneError::Gltf(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<gltf::Error> for SceneError {
+    fn from(error: gltf::Error) -> Self {
+        SceneError::Gltf(error)
+    }
+}
+
+impl Scene {
+    /// Imports a `.gltf` or `.glb` file. Buffers and images may be embedded, given as data URIs
+    /// or stored in files next to it.
+    pub fn import_gltf(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let (document, buffers, images) = gltf::import(path)?;
+        Self::from_gltf(&document, &buffers, images)
+    }
+
+    /// Imports a glTF file from memory. External buffers and images are not supported.
+    pub fn import_gltf_slice(data: &[u8]) -> Result<Self, SceneError> {
+        let (document, buffers, images) = gltf::import_slice(data)?;
+        Self::from_gltf(&document, &buffers, images)
+    }
+
+    fn from_gltf(
+        document: &gltf::Document,
+        buffers: &[gltf::buffer::Data],
+        images: Vec<gltf::image::Data>,
+    ) -> Result<Self, SceneError> {
+        let images = images
+            .into_iter()
+            .enumerate()
+            .map(|(i, image)| {
+                convert_image(i, image).map(|image| image.with_mipmaps(MipmapFilter::Box))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let textures = document
+            .textures()
+            .map(|texture| Texture {
+                image: texture.source().index(),
+                sampler: convert_sampler(&texture.sampler()),
+            })
+            .collect();
+        let materials = document
+            .materials()
+            .filter(|material| material.index().is_some())
+            .map(|material| {
+                let pbr = material.pbr_metallic_roughness();
+                Material {
+                    name: material.name().unwrap_or_default().to_owned(),
+                    base_color: pbr.base_color_factor().into(),
+                    base_color_texture: pbr.base_color_texture().map(|info| info.texture().index()),
+                }
+            })
+            .collect();
+        let meshes = document
+            .meshes()
+            .map(|mesh| {
+                let primitives = mesh
+                    .primitives()
+                    .map(|primitive| convert_primitive(mesh.index(), &primitive, buffers))
+                    .collect::<Result<_, _>>()?;
+                Ok(Mesh {
+                    name: mesh.name().unwrap_or_default().to_owned(),
+                    primitives,
+                })
+            })
+            .collect::<Result<_, SceneError>>()?;
+        let cameras = document
+            .cameras()
+            .map(|camera| Camera {
+                name: camera.name().unwrap_or_default().to_owned(),
+                projection: match camera.projection() {
+                    GltfProjection::Perspective(p) => Projection::Perspective {
+                        aspect_ratio: p.aspect_ratio(),
+                        y_fov: p.yfov(),
+                        z_near: p.znear(),
+                        z_far: p.zfar(),
+                    },
+                    GltfProjection::Orthographic(o) => Projection::Orthographic {
+                        x_mag: o.xmag(),
+                        y_mag: o.ymag(),
+                        z_near: o.znear(),
+                        z_far: o.zfar(),
+                    },
+                },
+            })
+            .collect();
+        let nodes = document
+            .nodes()
+            .map(|node| Node {
+                name: node.name().unwrap_or_default().to_owned(),
+                transform: Mat4::from(node.transform().matrix()),
+                children: node.children().map(|child| child.index()).collect(),
+                mesh: node.mesh().map(|mesh| mesh.index()),
+                camera: node.camera().map(|camera| camera.index()),
+            })
+            .collect::<Vec<_>>();
+        validate_hierarchy(&nodes)?;
+        let roots = document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+            .map_or_else(Vec::new, |scene| {
+                scene.nodes().map(|node| node.index()).collect()
+            });
+
+        Ok(Self {
+            meshes,
+            materials,
+            images,
+            textures,
+            cameras,
+            nodes,
+            roots,
+        })
+    }
+
+    /// Transform of every node relative to the scene, indexed like [`Scene::nodes`]. Nodes outside
+    /// the default scene keep the identity.
+    pub fn world_transforms(&self) -> Vec<Mat4> {
+        let mut transforms = vec![Mat4::identity(); self.nodes.len()];
+        let mut stack = self
+            .roots
+            .iter()
+            .map(|&root| (root, Mat4::identity()))
+            .collect::<Vec<_>>();
+        while let Some((node, parent)) = stack.pop() {
+            transforms[node] = parent * self.nodes[node].transform;
+            stack.extend(
+                self.nodes[node]
+                    .children
+                    .iter()
+                    .map(|&child| (child, transforms[node])),
+            );
+        }
+        transforms
+    }
+}
+
+impl Projection {
+    /// Projection matrix for a viewport of the given aspect ratio. glTF cameras look down -Z with
+    /// +Y up, so Y is flipped to match framebuffer rows, which grow downwards.
+    pub fn matrix(&self, viewport_aspect_ratio: f32) -> Mat4 {
+        let projection = match *self {
+            Projection::Perspective {
+                aspect_ratio,
+                y_fov,
+                z_near,
+                z_far: Some(z_far),
+            } => nalgebra_glm::perspective_rh_zo(
+                aspect_ratio.unwrap_or(viewport_aspect_ratio),
+                y_fov,
+                z_near,
+                z_far,
+            ),
+            Projection::Perspective {
+                aspect_ratio,
+                y_fov,
+                z_near,
+                z_far: None,
+            } => nalgebra_glm::infinite_perspective_rh_zo(
+                aspect_ratio.unwrap_or(viewport_aspect_ratio),
+                y_fov,
+                z_near,
+            ),
+            Projection::Orthographic {
+                x_mag,
+                y_mag,
+                z_near,
+                z_far,
+            } => nalgebra_glm::ortho_rh_zo(-x_mag, x_mag, -y_mag, y_mag, z_near, z_far),
+        };
+        Mat4::new_nonuniform_scaling(&Vec3::new(1.0, -1.0, 1.0)) * projection
+    }
+}
+
+fn convert_primitive(
+    mesh: usize,
+    primitive: &gltf::Primitive,
+    buffers: &[gltf::buffer::Data],
+) -> Result<MeshPrimitive, SceneError> {
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+    let positions = reader
+        .read_positions()
+        .ok_or(SceneError::MissingPositions { mesh })?;
+    let mut normals = reader.read_normals();
+    let mut uvs = reader.read_tex_coords(0).map(|uvs| uvs.into_f32());
+    let mut colors = reader.read_colors(0).map(|colors| colors.into_rgba_f32());
+    let vertices = positions
+        .map(|position| {
+            let normal = normals.as_mut().and_then(Iterator::next);
+            let uv = uvs.as_mut().and_then(Iterator::next);
+            let color = colors.as_mut().and_then(Iterator::next);
+            Vertex::new(
+                position.into(),
+                color.map_or(WHITE, Color::from),
+                uv.map_or(Vec2::zeros(), |[u, v]| vec2(u, v)),
+            )
+            .with_normal(normal.map_or(Vec3::zeros(), Vec3::from))
+        })
+        .collect::<Vec<_>>();
+    let mut indices = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..vertices.len() as u32).collect::<Vec<_>>(),
+    };
+    if let Some(&index) = indices.iter().find(|&&i| i as usize >= vertices.len()) {
+        return Err(SceneError::IndexOutOfRange { mesh, index });
+    }
+    let topology = match primitive.mode() {
+        Mode::Points => PrimitiveTopology::PointList,
+        Mode::Lines => PrimitiveTopology::LineList,
+        Mode::LineStrip => PrimitiveTopology::LineStrip,
+        Mode::LineLoop => {
+            // A loop is a strip that returns to its first vertex.
+            if let Some(&first) = indices.first() {
+                indices.push(first);
+            }
+            PrimitiveTopology::LineStrip
+        }
+        Mode::Triangles => PrimitiveTopology::TriangleList,
+        Mode::TriangleStrip => PrimitiveTopology::TriangleStrip,
+        Mode::TriangleFan => PrimitiveTopology::TriangleFan,
+    };
+    Ok(MeshPrimitive {
+        topology,
+        vertices,
+        indices,
+        material: primitive.material().index(),
+    })
+}
+
+/// Checks that the nodes form a forest, which glTF requires but does not enforce, so that
+/// walking down from the roots terminates.
+fn validate_hierarchy(nodes: &[Node]) -> Result<(), SceneError> {
+    let mut has_parent = vec![false; nodes.len()];
+    for &child in nodes.iter().flat_map(|node| &node.children) {
+        if std::mem::replace(&mut has_parent[child], true) {
+            return Err(SceneError::InvalidNodeHierarchy { node: child });
+        }
+    }
+    // With at most one parent each, the nodes that cannot be reached from a parentless node are
+    // exactly those on a cycle.
+    let mut reached = has_parent.iter().map(|&p| !p).collect::<Vec<_>>();
+    let mut stack = (0..nodes.len()).filter(|&i| reached[i]).collect::<Vec<_>>();
+    while let Some(node) = stack.pop() {
+        for &child in &nodes[node].children {
+            reached[child] = true;
+            stack.push(child);
+        }
+    }
+    match reached.iter().position(|&reached| !reached) {
+        Some(node) => Err(SceneError::InvalidNodeHierarchy { node }),
+        None => Ok(()),
+    }
+}
+
+fn convert_sampler(sampler: &gltf::texture::Sampler) -> Sampler {
+    let address_mode = |mode| match mode {
+        WrappingMode::ClampToEdge => AddressMode::Clamp,
+        WrappingMode::MirroredRepeat => AddressMode::MirroredRepeat,
+        WrappingMode::Repeat => AddressMode::Repeat,
+    };
+    let min_filter = match sampler.min_filter() {
+        Some(
+            MinFilter::Nearest | MinFilter::NearestMipmapNearest | MinFilter::NearestMipmapLinear,
+        ) => Filter::Nearest,
+        _ => Filter::Linear,
+    };
+    let mag_filter = match sampler.mag_filter() {
+        Some(MagFilter::Nearest) => Filter::Nearest,
+        _ => Filter::Linear,
+    };
+    let converted = Sampler::new(
+        address_mode(sampler.wrap_s()),
+        address_mode(sampler.wrap_t()),
+        min_filter,
+        mag_filter,
+    );
+    match sampler.min_filter() {
+        Some(MinFilter::NearestMipmapNearest | MinFilter::LinearMipmapNearest) => {
+            converted.with_mipmap_mode(MipmapMode::Nearest)
+        }
+        // Filters without mipmapping only ever sample the base level.
+        Some(MinFilter::Nearest | MinFilter::Linear) => converted.with_lod_clamp(0.0, 0.0),
+        _ => converted.with_mipmap_mode(MipmapMode::Linear),
+    }
+}
+
+fn convert_image(index: usize, image: gltf::image::Data) -> Result<Image, SceneError> {
+    let (channels, bytes_per_channel) = match image.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        format => {
+            return Err(SceneError::UnsupportedImageFormat {
+                image: index,
+                format,
+            })
+        }
+    };
+    let buffer = image
+        .pixels
+        .chunks_exact(channels * bytes_per_channel)
+        .map(|pixel| {
+            // Keep the most significant byte of each native-endian channel.
+            let channel = |c: usize| {
+                let bytes = &pixel[c * bytes_per_channel..(c + 1) * bytes_per_channel];
+                match bytes_per_channel {
+                    1 => bytes[0] as u32,
+                    _ => (u16::from_ne_bytes([bytes[0], bytes[1]]) >> 8) as u32,
+                }
+            };
+            let [r, g, b, a] = match channels {
+                1 => [channel(0), channel(0), channel(0), 0xFF],
+                2 => [channel(0), channel(0), channel(0), channel(1)],
+                3 => [channel(0), channel(1), channel(2), 0xFF],
+                _ => [channel(0), channel(1), channel(2), channel(3)],
+            };
+            (a << 24) | (r << 16) | (g << 8) | b
+        })
+        .collect();
+    // Only base color textures are imported, and glTF stores those in sRGB.
+    Ok(
+        Image::from_buffer(buffer, image.width as usize, image.height as usize)
+            .with_color_space(ColorSpace::Srgb),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use nalgebra_glm::vec4;
+
+    use super::*;
+    use crate::{
+        color::{BLACK, BLUE, RED},
+        framebuffer::Framebuffer,
+        pipeline::RasterizationPipeline,
+        shader::{FlatColorShader, TransformShader},
+        viewport::Viewport,
+    };
+
+    fn triangle_scene() -> Scene {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("tests/assets/triangle.gltf");
+        Scene::import_gltf(path).unwrap()
+    }
+
+    #[test]
+    fn imports_meshes_materials_and_textures() {
+        let scene = triangle_scene();
+
+        let primitive = &scene.meshes[0].primitives[0];
+        assert_eq!(primitive.topology, PrimitiveTopology::TriangleList);
+        assert_eq!(primitive.indices, [0, 1, 2]);
+        assert_eq!(primitive.vertices[2].coords, vec4(0.0, 1.0, 0.0, 1.0));
+        assert_eq!(primitive.vertices[2].uv, vec2(0.5, 0.0));
+
+        let material = &scene.materials[primitive.material.unwrap()];
+        assert_eq!(material.base_color, Color::new(1.0, 1.0, 1.0, 0.5));
+        let texture = &scene.textures[material.base_color_texture.unwrap()];
+        assert_eq!(
+            texture.sampler,
+            Sampler::new(
+                AddressMode::Clamp,
+                AddressMode::Repeat,
+                Filter::Linear,
+                Filter::Nearest
+            )
+            .with_mipmap_mode(MipmapMode::Linear)
+        );
+        let image = &scene.images[texture.image];
+        assert_eq!(image.get_color((0, 0)), RED);
+        assert_eq!(image.get_color((1, 0)), BLUE);
+        assert_eq!(image.mip_level_count(), 2);
+        assert_eq!(image.color_space(), ColorSpace::Srgb);
+    }
+
+    #[test]
+    fn node_transforms_are_composed() {
+        let scene = triangle_scene();
+
+        let transforms = scene.world_transforms();
+
+        assert_eq!(scene.roots, [0, 2]);
+        assert_eq!(scene.nodes[0].children, [1]);
+        assert_eq!(
+            transforms[1] * vec4(0.0, 1.0, 0.0, 1.0),
+            vec4(1.0, 2.0, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn node_hierarchies_must_be_trees() {
+        let import = |nodes: &str| {
+            let gltf = format!(
+                r#"{{"asset":{{"version":"2.0"}},"scenes":[{{"nodes":[0]}}],"nodes":{nodes}}}"#
+            );
+            Scene::import_gltf_slice(gltf.as_bytes())
+                .err()
+                .map(|e| e.to_string())
+        };
+
+        assert_eq!(
+            import(r#"[{"children":[1]},{"children":[0]}]"#).unwrap(),
+            "node 0 has several parents or is part of a cycle"
+        );
+        assert_eq!(
+            import(r#"[{"children":[1,2]},{"children":[2]},{}]"#).unwrap(),
+            "node 2 has several parents or is part of a cycle"
+        );
+        assert_eq!(
+            import(r#"[{"children":[1]},{},{"children":[3]},{"children":[2]}]"#).unwrap(),
+            "node 2 has several parents or is part of a cycle"
+        );
+        assert_eq!(
+            import(r#"[{"children":[1,2]},{},{"children":[3]},{}]"#),
+            None
+        );
+    }
+
+    #[test]
+    fn indices_must_refer_to_vertices() {
+        // Three positions followed by the indices 0, 1 and 5.
+        let gltf = r#"{
+            "asset": {"version": "2.0"},
+            "buffers": [{
+                "byteLength": 44,
+                "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAABAAUAAAA="
+            }],
+            "bufferViews": [
+                {"buffer": 0, "byteLength": 36},
+                {"buffer": 0, "byteOffset": 36, "byteLength": 6}
+            ],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                 "min": [0, 0, 0], "max": [1, 1, 0]},
+                {"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}
+            ],
+            "meshes": [{"primitives": [{"attributes": {"POSITION": 0}, "indices": 1}]}]
+        }"#;
+
+        assert_eq!(
+            Scene::import_gltf_slice(gltf.as_bytes())
+                .err()
+                .unwrap()
+                .to_string(),
+            "a primitive of mesh 0 refers to missing vertex 5"
+        );
+    }
+
+    #[test]
+    fn cameras_render_front_faces_upright() {
+        let scene = triangle_scene();
+        let transforms = scene.world_transforms();
+        let camera = &scene.cameras[scene.nodes[2].camera.unwrap()];
+        let view = transforms[2].try_inverse().unwrap();
+        let (width, height) = (32, 32);
+        let mut framebuffer = Framebuffer::new(width, height);
+        let pipeline = RasterizationPipeline::new(Viewport::full(width as f32, height as f32));
+        let primitive = &scene.meshes[0].primitives[0];
+        let transform = camera.projection.matrix(1.0) * view * transforms[1];
+
+        framebuffer.clear(BLACK, f32::INFINITY);
+        pipeline.draw_indexed(
+            &mut framebuffer,
+            primitive.topology,
+            (&TransformShader::new(transform), &FlatColorShader::new(RED)),
+            &primitive.vertices,
+            &primitive.indices,
+        );
+
+        // The apex of the triangle points up, so the top row is only covered in the middle.
+        assert_eq!(framebuffer.get_color((16, 16)), RED);
+        assert_eq!(framebuffer.get_color((16, 6)), RED);
+        assert_eq!(framebuffer.get_color((4, 4)), BLACK);
+        assert_eq!(framebuffer.get_color((8, 26)), RED);
+    }
+}
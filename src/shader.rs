@@ -0,0 +1,139 @@
+use nalgebra_glm::{Mat4, TVec2, Vec3};
+
+use crate::{
+    color::Color,
+    image::Image,
+    sampler::Sampler,
+    varyings::Varyings,
+    vertex::{interpolate_triangle, ClipVertex, Vertex},
+};
+
+/// Per-vertex stage of the pipeline. Uniforms are the fields of the implementing type.
+pub trait VertexShader {
+    type Input;
+    type Varyings: Varyings;
+
+    fn shade(&self, input: &Self::Input) -> ClipVertex<Self::Varyings>;
+}
+
+/// Per-fragment stage of the pipeline. Uniforms are the fields of the implementing type.
+pub trait FragmentShader<V> {
+    fn shade(&self, fragment: &FragmentInput<V>) -> Color;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FragmentInput<'a, V> {
+    pub coords: TVec2<usize>,
+    pub depth: f32,
+    pub front_facing: bool,
+    pub varyings: V,
+    triangle: &'a [ClipVertex<V>; 3],
+    t: Vec3,
+    dt_dx: Vec3,
+    dt_dy: Vec3,
+}
+
+impl<'a, V: Varyings> FragmentInput<'a, V> {
+    pub fn new(
+        coords: TVec2<usize>,
+        triangle: &'a [ClipVertex<V>; 3],
+        t: Vec3,
+        dt_dx: Vec3,
+        dt_dy: Vec3,
+        front_facing: bool,
+    ) -> Self {
+        let ClipVertex { position, varyings } = interpolate_triangle(triangle, t);
+        Self {
+            coords,
+            depth: position.z,
+            front_facing,
+            varyings,
+            triangle,
+            t,
+            dt_dx,
+            dt_dy,
+        }
+    }
+
+    /// Rate of change of the varyings along the screen's horizontal axis.
+    pub fn ddx(&self) -> V {
+        interpolate_triangle(self.triangle, self.t + self.dt_dx)
+            .varyings
+            .difference(&self.varyings)
+    }
+
+    /// Rate of change of the varyings along the screen's vertical axis.
+    pub fn ddy(&self) -> V {
+        interpolate_triangle(self.triangle, self.t + self.dt_dy)
+            .varyings
+            .difference(&self.varyings)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TransformShader {
+    pub transform: Mat4,
+}
+
+impl TransformShader {
+    pub fn new(transform: Mat4) -> Self {
+        Self { transform }
+    }
+}
+
+impl VertexShader for TransformShader {
+    type Input = Vertex;
+    type Varyings = Vertex;
+
+    fn shade(&self, input: &Vertex) -> ClipVertex<Vertex> {
+        ClipVertex::new(self.transform * input.coords, *input)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FlatColorShader {
+    pub color: Color,
+}
+
+impl FlatColorShader {
+    pub fn new(color: Color) -> Self {
+        Self { color }
+    }
+}
+
+impl<V> FragmentShader<V> for FlatColorShader {
+    fn shade(&self, _: &FragmentInput<V>) -> Color {
+        self.color
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VertexColorShader;
+
+impl FragmentShader<Vertex> for VertexColorShader {
+    fn shade(&self, fragment: &FragmentInput<Vertex>) -> Color {
+        fragment.varyings.color
+    }
+}
+
+pub struct TextureShader<'a> {
+    pub image: &'a Image,
+    pub sampler: &'a Sampler,
+}
+
+impl<'a> TextureShader<'a> {
+    pub fn new(image: &'a Image, sampler: &'a Sampler) -> Self {
+        Self { image, sampler }
+    }
+}
+
+impl FragmentShader<Vertex> for TextureShader<'_> {
+    fn shade(&self, fragment: &FragmentInput<Vertex>) -> Color {
+        self.sampler.sample(
+            self.image,
+            fragment.varyings.uv,
+            fragment.ddx().uv,
+            fragment.ddy().uv,
+        )
+    }
+}
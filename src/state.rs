@@ -0,0 +1,485 @@
+use std::ops::BitOr;
+
+use crate::color::Color;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CullMode {
+    None,
+    Front,
+    #[default]
+    Back,
+}
+
+/// Winding order of front-facing triangles, as seen on the framebuffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrontFace {
+    Clockwise,
+    #[default]
+    CounterClockwise,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RasterizerState {
+    pub cull_mode: CullMode,
+    pub front_face: FrontFace,
+}
+
+impl RasterizerState {
+    pub fn new(cull_mode: CullMode, front_face: FrontFace) -> Self {
+        Self {
+            cull_mode,
+            front_face,
+        }
+    }
+
+    /// Whether a triangle with the given signed area in framebuffer coordinates faces the viewer.
+    /// Counterclockwise triangles have a positive area.
+    pub fn is_front_facing(&self, signed_area: f32) -> bool {
+        match self.front_face {
+            FrontFace::CounterClockwise => signed_area > 0.0,
+            FrontFace::Clockwise => signed_area < 0.0,
+        }
+    }
+
+    pub fn is_culled(&self, front_facing: bool) -> bool {
+        match self.cull_mode {
+            CullMode::None => false,
+            CullMode::Front => front_facing,
+            CullMode::Back => !front_facing,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompareOp {
+    Never,
+    #[default]
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl CompareOp {
+    /// Compares an incoming value against the one already stored in the framebuffer.
+    pub fn test<T: PartialOrd>(self, value: T, stored: T) -> bool {
+        match self {
+            CompareOp::Never => false,
+            CompareOp::Less => value < stored,
+            CompareOp::Equal => value == stored,
+            CompareOp::LessEqual => value <= stored,
+            CompareOp::Greater => value > stored,
+            CompareOp::NotEqual => value != stored,
+            CompareOp::GreaterEqual => value >= stored,
+            CompareOp::Always => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StencilOp {
+    #[default]
+    Keep,
+    Zero,
+    Replace,
+    IncrementAndClamp,
+    DecrementAndClamp,
+    Invert,
+    IncrementAndWrap,
+    DecrementAndWrap,
+}
+
+impl StencilOp {
+    pub fn apply(self, stored: u8, reference: u8) -> u8 {
+        match self {
+            StencilOp::Keep => stored,
+            StencilOp::Zero => 0,
+            StencilOp::Replace => reference,
+            StencilOp::IncrementAndClamp => stored.saturating_add(1),
+            StencilOp::DecrementAndClamp => stored.saturating_sub(1),
+            StencilOp::Invert => !stored,
+            StencilOp::IncrementAndWrap => stored.wrapping_add(1),
+            StencilOp::DecrementAndWrap => stored.wrapping_sub(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StencilFaceState {
+    pub fail_op: StencilOp,
+    pub pass_op: StencilOp,
+    pub depth_fail_op: StencilOp,
+    pub compare_op: CompareOp,
+    pub compare_mask: u8,
+    pub write_mask: u8,
+    pub reference: u8,
+}
+
+impl Default for StencilFaceState {
+    fn default() -> Self {
+        Self {
+            fail_op: StencilOp::Keep,
+            pass_op: StencilOp::Keep,
+            depth_fail_op: StencilOp::Keep,
+            compare_op: CompareOp::Always,
+            compare_mask: 0xFF,
+            write_mask: 0xFF,
+            reference: 0,
+        }
+    }
+}
+
+impl StencilFaceState {
+    pub fn new(compare_op: CompareOp, reference: u8) -> Self {
+        Self {
+            compare_op,
+            reference,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_ops(
+        mut self,
+        fail_op: StencilOp,
+        depth_fail_op: StencilOp,
+        pass_op: StencilOp,
+    ) -> Self {
+        self.fail_op = fail_op;
+        self.depth_fail_op = depth_fail_op;
+        self.pass_op = pass_op;
+        self
+    }
+
+    pub fn with_masks(mut self, compare_mask: u8, write_mask: u8) -> Self {
+        self.compare_mask = compare_mask;
+        self.write_mask = write_mask;
+        self
+    }
+
+    pub fn test(&self, stored: u8) -> bool {
+        self.compare_op.test(
+            self.reference & self.compare_mask,
+            stored & self.compare_mask,
+        )
+    }
+
+    /// Returns the new stencil value after applying `op`, keeping the bits outside the write mask.
+    pub fn update(&self, op: StencilOp, stored: u8) -> u8 {
+        (stored & !self.write_mask) | (op.apply(stored, self.reference) & self.write_mask)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepthStencilState {
+    pub depth_test_enable: bool,
+    pub depth_write_enable: bool,
+    pub depth_compare_op: CompareOp,
+    pub stencil_test_enable: bool,
+    pub front: StencilFaceState,
+    pub back: StencilFaceState,
+}
+
+impl Default for DepthStencilState {
+    fn default() -> Self {
+        Self {
+            depth_test_enable: true,
+            depth_write_enable: true,
+            depth_compare_op: CompareOp::Less,
+            stencil_test_enable: false,
+            front: StencilFaceState::default(),
+            back: StencilFaceState::default(),
+        }
+    }
+}
+
+impl DepthStencilState {
+    pub fn new(depth_compare_op: CompareOp, depth_write_enable: bool) -> Self {
+        Self {
+            depth_write_enable,
+            depth_compare_op,
+            ..Default::default()
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self {
+            depth_test_enable: false,
+            depth_write_enable: false,
+            depth_compare_op: CompareOp::Always,
+            ..Default::default()
+        }
+    }
+
+    /// Enables the stencil test, with separate states for front and back-facing primitives.
+    pub fn with_stencil(mut self, front: StencilFaceState, back: StencilFaceState) -> Self {
+        self.stencil_test_enable = true;
+        self.front = front;
+        self.back = back;
+        self
+    }
+
+    pub fn stencil_face(&self, front_facing: bool) -> &StencilFaceState {
+        if front_facing {
+            &self.front
+        } else {
+            &self.back
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SrcColor,
+    OneMinusSrcColor,
+    DstColor,
+    OneMinusDstColor,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    DstAlpha,
+    OneMinusDstAlpha,
+    ConstantColor,
+    OneMinusConstantColor,
+    SrcAlphaSaturate,
+}
+
+impl BlendFactor {
+    fn weights(self, src: Color, dst: Color, constant: Color) -> Color {
+        let one = Color::repeat(1.0);
+        match self {
+            BlendFactor::Zero => Color::zeros(),
+            BlendFactor::One => one,
+            BlendFactor::SrcColor => src,
+            BlendFactor::OneMinusSrcColor => one - src,
+            BlendFactor::DstColor => dst,
+            BlendFactor::OneMinusDstColor => one - dst,
+            BlendFactor::SrcAlpha => Color::repeat(src.w),
+            BlendFactor::OneMinusSrcAlpha => Color::repeat(1.0 - src.w),
+            BlendFactor::DstAlpha => Color::repeat(dst.w),
+            BlendFactor::OneMinusDstAlpha => Color::repeat(1.0 - dst.w),
+            BlendFactor::ConstantColor => constant,
+            BlendFactor::OneMinusConstantColor => one - constant,
+            BlendFactor::SrcAlphaSaturate => {
+                let f = src.w.min(1.0 - dst.w);
+                Color::new(f, f, f, 1.0)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendOp {
+    #[default]
+    Add,
+    Subtract,
+    ReverseSubtract,
+    Min,
+    Max,
+}
+
+impl BlendOp {
+    fn apply(self, src: f32, src_factor: f32, dst: f32, dst_factor: f32) -> f32 {
+        match self {
+            BlendOp::Add => src * src_factor + dst * dst_factor,
+            BlendOp::Subtract => src * src_factor - dst * dst_factor,
+            BlendOp::ReverseSubtract => dst * dst_factor - src * src_factor,
+            BlendOp::Min => src.min(dst),
+            BlendOp::Max => src.max(dst),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColorWriteMask(u8);
+
+impl ColorWriteMask {
+    pub const NONE: Self = Self(0);
+    pub const R: Self = Self(1 << 0);
+    pub const G: Self = Self(1 << 1);
+    pub const B: Self = Self(1 << 2);
+    pub const A: Self = Self(1 << 3);
+    pub const ALL: Self = Self(0b1111);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for ColorWriteMask {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlendState {
+    pub blend_enable: bool,
+    pub src_color_factor: BlendFactor,
+    pub dst_color_factor: BlendFactor,
+    pub color_op: BlendOp,
+    pub src_alpha_factor: BlendFactor,
+    pub dst_alpha_factor: BlendFactor,
+    pub alpha_op: BlendOp,
+    pub write_mask: ColorWriteMask,
+    pub constant: Color,
+}
+
+impl Default for BlendState {
+    fn default() -> Self {
+        Self {
+            blend_enable: false,
+            src_color_factor: BlendFactor::One,
+            dst_color_factor: BlendFactor::Zero,
+            color_op: BlendOp::Add,
+            src_alpha_factor: BlendFactor::One,
+            dst_alpha_factor: BlendFactor::Zero,
+            alpha_op: BlendOp::Add,
+            write_mask: ColorWriteMask::ALL,
+            constant: Color::zeros(),
+        }
+    }
+}
+
+impl BlendState {
+    /// Standard "over" compositing of straight-alpha colors.
+    pub fn alpha_blending() -> Self {
+        Self {
+            blend_enable: true,
+            src_color_factor: BlendFactor::SrcAlpha,
+            dst_color_factor: BlendFactor::OneMinusSrcAlpha,
+            src_alpha_factor: BlendFactor::One,
+            dst_alpha_factor: BlendFactor::OneMinusSrcAlpha,
+            ..Default::default()
+        }
+    }
+
+    pub fn additive() -> Self {
+        Self {
+            blend_enable: true,
+            src_color_factor: BlendFactor::One,
+            dst_color_factor: BlendFactor::One,
+            src_alpha_factor: BlendFactor::One,
+            dst_alpha_factor: BlendFactor::One,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_write_mask(mut self, write_mask: ColorWriteMask) -> Self {
+        self.write_mask = write_mask;
+        self
+    }
+
+    /// Whether the color already in the framebuffer is needed to compute the output.
+    pub fn reads_destination(&self) -> bool {
+        self.blend_enable || self.write_mask != ColorWriteMask::ALL
+    }
+
+    /// Combines the fragment color `src` with the color `dst` already in the framebuffer.
+    pub fn blend(&self, src: Color, dst: Color) -> Color {
+        let blended = if self.blend_enable {
+            let src_color = self.src_color_factor.weights(src, dst, self.constant);
+            let dst_color = self.dst_color_factor.weights(src, dst, self.constant);
+            let src_alpha = self.src_alpha_factor.weights(src, dst, self.constant);
+            let dst_alpha = self.dst_alpha_factor.weights(src, dst, self.constant);
+            Color::new(
+                self.color_op.apply(src.x, src_color.x, dst.x, dst_color.x),
+                self.color_op.apply(src.y, src_color.y, dst.y, dst_color.y),
+                self.color_op.apply(src.z, src_color.z, dst.z, dst_color.z),
+                self.alpha_op.apply(src.w, src_alpha.w, dst.w, dst_alpha.w),
+            )
+        } else {
+            src
+        };
+        let channels = [
+            ColorWriteMask::R,
+            ColorWriteMask::G,
+            ColorWriteMask::B,
+            ColorWriteMask::A,
+        ];
+        Color::from_fn(|i, _| {
+            if self.write_mask.contains(channels[i]) {
+                blended[i]
+            } else {
+                dst[i]
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_ops() {
+        let ops = [
+            CompareOp::Never,
+            CompareOp::Less,
+            CompareOp::Equal,
+            CompareOp::LessEqual,
+            CompareOp::Greater,
+            CompareOp::NotEqual,
+            CompareOp::GreaterEqual,
+            CompareOp::Always,
+        ];
+
+        assert_eq!(
+            ops.map(|op| [op.test(0.0, 0.5), op.test(0.5, 0.5), op.test(1.0, 0.5)]),
+            [
+                [false, false, false],
+                [true, false, false],
+                [false, true, false],
+                [true, true, false],
+                [false, false, true],
+                [true, false, true],
+                [false, true, true],
+                [true, true, true],
+            ]
+        );
+    }
+
+    #[test]
+    fn alpha_blending() {
+        let blend_state = BlendState::alpha_blending();
+        let src = Color::new(1.0, 0.0, 0.0, 0.25);
+        let dst = Color::new(0.0, 0.0, 1.0, 1.0);
+
+        assert_eq!(
+            blend_state.blend(src, dst),
+            Color::new(0.25, 0.0, 0.75, 1.0)
+        );
+    }
+
+    #[test]
+    fn write_mask_keeps_masked_channels() {
+        let blend_state =
+            BlendState::default().with_write_mask(ColorWriteMask::R | ColorWriteMask::A);
+        let src = Color::new(1.0, 1.0, 1.0, 0.5);
+        let dst = Color::new(0.0, 0.25, 0.5, 1.0);
+
+        assert_eq!(blend_state.blend(src, dst), Color::new(1.0, 0.25, 0.5, 0.5));
+    }
+
+    #[test]
+    fn stencil_update_respects_write_mask() {
+        let face = StencilFaceState::new(CompareOp::Always, 0b1010).with_masks(0xFF, 0b0011);
+
+        assert_eq!(face.update(StencilOp::Replace, 0b0101), 0b0110);
+        assert_eq!(face.update(StencilOp::Invert, 0b0101), 0b0110);
+        assert_eq!(face.update(StencilOp::DecrementAndWrap, 0b0100), 0b0111);
+    }
+
+    #[test]
+    fn stencil_test_respects_compare_mask() {
+        let face = StencilFaceState::new(CompareOp::Equal, 0b0001).with_masks(0b0011, 0xFF);
+
+        assert!(face.test(0b1101));
+        assert!(!face.test(0b1110));
+    }
+}
@@ -0,0 +1,233 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    fs, io,
+    path::Path,
+};
+
+use nalgebra_glm::{vec2, vec3, Vec3};
+
+use crate::{color::WHITE, vertex::Vertex};
+
+/// Size of the header preceding the triangle count in binary files.
+const BINARY_HEADER_SIZE: usize = 80;
+/// Size of a binary triangle: normal, three vertices and an attribute byte count.
+const BINARY_TRIANGLE_SIZE: usize = 50;
+
+#[derive(Debug)]
+pub enum StlError {
+    Io(io::Error),
+    Parse(String),
+}
+
+impl Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StlError::Io(error) => write!(f, "cannot read STL file: {error}"),
+            StlError::Parse(message) => write!(f, "invalid STL file: {message}"),
+        }
+    }
+}
+
+impl Error for StlError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StlError::Io(error) => Some(error),
+            StlError::Parse(_) => None,
+        }
+    }
+}
+
+/// Loads the triangles of an ASCII or binary STL file.
+pub fn load_stl(path: impl AsRef<Path>) -> Result<(Vec<Vertex>, Vec<u32>), StlError> {
+    parse_stl(&fs::read(path).map_err(StlError::Io)?)
+}
+
+/// Parses an STL file. Every triangle gets its own three vertices with a flat normal computed
+/// from its winding, since the normals stored in STL files are often missing or wrong. Degenerate
+/// triangles fall back to the stored normal, or to zero if that is unusable too.
+pub fn parse_stl(data: &[u8]) -> Result<(Vec<Vertex>, Vec<u32>), StlError> {
+    // Some exporters start binary files with `solid` too, so trust the size first.
+    let triangles = if is_binary(data) {
+        parse_binary(data)
+    } else if data.trim_ascii_start().starts_with(b"solid") {
+        parse_ascii(data)?
+    } else {
+        return Err(StlError::Parse(
+            "neither an ASCII nor a binary STL file".to_owned(),
+        ));
+    };
+
+    let vertices = triangles
+        .iter()
+        .flat_map(|(stored_normal, triangle)| {
+            let normal = (triangle[1] - triangle[0])
+                .cross(&(triangle[2] - triangle[0]))
+                .try_normalize(f32::EPSILON)
+                .or_else(|| stored_normal.try_normalize(f32::EPSILON))
+                .unwrap_or_else(Vec3::zeros);
+            triangle.map(|c| Vertex::new(c, WHITE, vec2(0.0, 0.0)).with_normal(normal))
+        })
+        .collect::<Vec<_>>();
+    let indices = (0..vertices.len() as u32).collect();
+    Ok((vertices, indices))
+}
+
+fn is_binary(data: &[u8]) -> bool {
+    let Some(count) = data.get(BINARY_HEADER_SIZE..BINARY_HEADER_SIZE + 4) else {
+        return false;
+    };
+    let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+    data.len() == BINARY_HEADER_SIZE + 4 + count * BINARY_TRIANGLE_SIZE
+}
+
+/// Reads the stored normal and the vertices of every triangle.
+fn parse_binary(data: &[u8]) -> Vec<(Vec3, [Vec3; 3])> {
+    let read_vec3 = |bytes: &[u8]| {
+        let [x, y, z] = [0, 4, 8].map(|i| f32::from_le_bytes(bytes[i..i + 4].try_into().unwrap()));
+        vec3(x, y, z)
+    };
+    data[BINARY_HEADER_SIZE + 4..]
+        .chunks_exact(BINARY_TRIANGLE_SIZE)
+        .map(|triangle| {
+            let vertices = [12, 24, 36].map(|offset| read_vec3(&triangle[offset..]));
+            (read_vec3(triangle), vertices)
+        })
+        .collect()
+}
+
+fn parse_ascii(data: &[u8]) -> Result<Vec<(Vec3, [Vec3; 3])>, StlError> {
+    let text = std::str::from_utf8(data)
+        .map_err(|_| StlError::Parse("ASCII file is not valid UTF-8".to_owned()))?;
+    let mut triangles = Vec::new();
+    let mut normal = Vec3::zeros();
+    let mut facet = Vec::with_capacity(3);
+    for (number, line) in text.lines().enumerate() {
+        let error = |message: &str| StlError::Parse(format!("line {}: {message}", number + 1));
+        let mut tokens = line.split_whitespace();
+        let read_vec3 = |tokens: std::str::SplitWhitespace| {
+            let coords = tokens
+                .map(str::parse)
+                .collect::<Result<Vec<f32>, _>>()
+                .map_err(|_| error("invalid coordinate"))?;
+            let [x, y, z] = coords[..] else {
+                return Err(error("expected three coordinates"));
+            };
+            Ok(vec3(x, y, z))
+        };
+        match tokens.next() {
+            // A malformed stored normal is harmless, it is only a fallback.
+            Some("facet") if tokens.next() == Some("normal") => {
+                normal = read_vec3(tokens).unwrap_or_else(|_| Vec3::zeros());
+            }
+            Some("vertex") => facet.push(read_vec3(tokens)?),
+            Some("endfacet") => {
+                let triangle = <[Vec3; 3]>::try_from(facet.as_slice())
+                    .map_err(|_| error("facet does not have three vertices"))?;
+                triangles.push((normal, triangle));
+                facet.clear();
+                normal = Vec3::zeros();
+            }
+            _ => {}
+        }
+    }
+    Ok(triangles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_triangles_get_flat_normals() {
+        let data = b"solid scan
+facet normal 0 0 0
+  outer loop
+    vertex 0 0 0
+    vertex 1 0 0
+    vertex 0 1 0
+  endloop
+endfacet
+facet normal 0 0 0
+  outer loop
+    vertex 0 0 0
+    vertex 0 0 1
+    vertex 1 0 0
+  endloop
+endfacet
+endsolid scan
+";
+
+        let (vertices, indices) = parse_stl(data).unwrap();
+
+        assert_eq!(indices, [0, 1, 2, 3, 4, 5]);
+        assert_eq!(vertices[1].coords, vec3(1.0, 0.0, 0.0).push(1.0));
+        assert!(vertices[..3]
+            .iter()
+            .all(|v| v.normal == vec3(0.0, 0.0, 1.0)));
+        assert!(vertices[3..]
+            .iter()
+            .all(|v| v.normal == vec3(0.0, 1.0, 0.0)));
+    }
+
+    #[test]
+    fn binary_files_may_start_with_solid() {
+        let mut data = b"solid but actually binary".to_vec();
+        data.resize(BINARY_HEADER_SIZE, 0);
+        data.extend(1u32.to_le_bytes());
+        for c in [
+            [0.0f32, 0.0, 1.0],
+            [0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 0.0, 0.0],
+        ] {
+            data.extend(c.iter().flat_map(|c| c.to_le_bytes()));
+        }
+        data.extend([0, 0]);
+
+        let (vertices, indices) = parse_stl(&data).unwrap();
+
+        assert_eq!(indices, [0, 1, 2]);
+        assert_eq!(vertices[2].coords, vec3(1.0, 0.0, 0.0).push(1.0));
+        assert_eq!(vertices[0].normal, vec3(0.0, 0.0, -1.0));
+        assert_eq!(vertices[0].color, WHITE);
+    }
+
+    #[test]
+    fn degenerate_triangles_fall_back_to_the_stored_normal() {
+        let data = b"solid
+facet normal 0 0 2
+  outer loop
+    vertex 0 0 0
+    vertex 1 1 1
+    vertex 2 2 2
+  endloop
+endfacet
+facet normal 0 0 0
+  outer loop
+    vertex 1 0 0
+    vertex 1 0 0
+    vertex 1 0 0
+  endloop
+endfacet
+endsolid
+";
+
+        let (vertices, _) = parse_stl(data).unwrap();
+
+        assert!(vertices[..3]
+            .iter()
+            .all(|v| v.normal == vec3(0.0, 0.0, 1.0)));
+        assert!(vertices[3..].iter().all(|v| v.normal == Vec3::zeros()));
+    }
+
+    #[test]
+    fn incomplete_facets_are_rejected() {
+        let data = b"solid\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nendloop\nendfacet\n";
+
+        assert_eq!(
+            parse_stl(data).err().unwrap().to_string(),
+            "invalid STL file: line 6: facet does not have three vertices"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use nalgebra_glm::{mat3, Mat3, Vec3};
+
+use crate::color::Color;
+
+/// Curve mapping unbounded linear colors into [0, 1] for display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToneMapOperator {
+    /// Clips every channel to [0, 1].
+    Clamp,
+    /// `c / (1 + c)` per channel, which never quite reaches white.
+    #[default]
+    Reinhard,
+    /// Stephen Hill's fit of the ACES reference rendering and output device transforms, with
+    /// a filmic toe and shoulder and some desaturation of bright colors.
+    AcesFitted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToneMapping {
+    operator: ToneMapOperator,
+    exposure: f32,
+}
+
+impl ToneMapping {
+    pub fn new(operator: ToneMapOperator) -> Self {
+        Self {
+            operator,
+            exposure: 0.0,
+        }
+    }
+
+    /// Exposure in stops: colors are scaled by `2^exposure` before the operator is applied.
+    pub fn with_exposure(mut self, exposure: f32) -> Self {
+        self.exposure = exposure;
+        self
+    }
+
+    pub fn operator(&self) -> ToneMapOperator {
+        self.operator
+    }
+
+    pub fn exposure(&self) -> f32 {
+        self.exposure
+    }
+
+    /// Maps a linear HDR color to a linear color in [0, 1]. Alpha is only clamped.
+    pub fn apply(&self, color: Color) -> Color {
+        let rgb = color.xyz().map(|c| c.max(0.0)) * self.exposure.exp2();
+        let rgb = match self.operator {
+            ToneMapOperator::Clamp => rgb,
+            ToneMapOperator::Reinhard => rgb.map(|c| c / (1.0 + c)),
+            ToneMapOperator::AcesFitted => aces_fitted(rgb),
+        };
+        rgb.map(|c| c.clamp(0.0, 1.0)).push(color.w.clamp(0.0, 1.0))
+    }
+}
+
+fn aces_fitted(rgb: Vec3) -> Vec3 {
+    // sRGB to the ACES rendering space, with the RRT saturation adjustment folded in.
+    #[rustfmt::skip]
+    let input: Mat3 = mat3(
+        0.59719, 0.35458, 0.04823,
+        0.07600, 0.90834, 0.01566,
+        0.02840, 0.13383, 0.83777,
+    );
+    // From the output device transform back to sRGB.
+    #[rustfmt::skip]
+    let output: Mat3 = mat3(
+        1.60475, -0.53108, -0.07367,
+        -0.10208, 1.10813, -0.00605,
+        -0.00327, -0.07276, 1.07602,
+    );
+    let rrt_and_odt_fit =
+        |v: f32| (v * (v + 0.0245786) - 0.000090537) / (v * (0.983729 * v + 0.432951) + 0.238081);
+    output * (input * rgb).map(rrt_and_odt_fit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::BLACK;
+
+    #[test]
+    fn operators_map_black_and_bright_colors() {
+        for operator in [
+            ToneMapOperator::Clamp,
+            ToneMapOperator::Reinhard,
+            ToneMapOperator::AcesFitted,
+        ] {
+            let tone_mapping = ToneMapping::new(operator);
+
+            assert!(tone_mapping.apply(BLACK).xyz().max() < 1e-3, "{operator:?}");
+            let bright = tone_mapping.apply(Color::new(1000.0, 1000.0, 1000.0, 1.0));
+            assert!(bright.xyz().min() > 0.99, "{operator:?}: {bright:?}");
+        }
+    }
+
+    #[test]
+    fn curves_compress_highlights() {
+        let reinhard = ToneMapping::new(ToneMapOperator::Reinhard);
+        let aces = ToneMapping::new(ToneMapOperator::AcesFitted);
+        let gray = |tone_mapping: ToneMapping, value: f32| {
+            tone_mapping.apply(Color::new(value, value, value, 1.0)).x
+        };
+
+        assert_eq!(gray(reinhard, 1.0), 0.5);
+        assert_eq!(gray(reinhard, 3.0), 0.75);
+        // ACES has a darker toe and a brighter shoulder, so it keeps more contrast.
+        assert!(gray(aces, 0.18) < gray(reinhard, 0.18));
+        assert!(gray(aces, 2.0) > gray(reinhard, 2.0));
+        assert!(gray(aces, 2.0) < gray(aces, 4.0));
+    }
+
+    #[test]
+    fn exposure_is_measured_in_stops() {
+        let tone_mapping = ToneMapping::new(ToneMapOperator::Reinhard);
+
+        assert_eq!(
+            tone_mapping
+                .with_exposure(2.0)
+                .apply(Color::new(0.25, 0.75, 0.0, 0.5)),
+            Color::new(0.5, 0.75, 0.0, 0.5)
+        );
+        assert_eq!(
+            tone_mapping
+                .with_exposure(-1.0)
+                .apply(Color::new(2.0, 0.0, 0.0, 1.0)),
+            tone_mapping.apply(Color::new(1.0, 0.0, 0.0, 1.0))
+        );
+    }
+}
@@ -0,0 +1,69 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrimitiveTopology {
+    PointList,
+    LineList,
+    LineStrip,
+    TriangleList,
+    TriangleStrip,
+    TriangleFan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Primitive {
+    Point(usize),
+    Line([usize; 2]),
+    Triangle([usize; 3]),
+}
+
+impl PrimitiveTopology {
+    /// Assembles `vertex_count` vertices into primitives, given as indices into the vertex stream.
+    pub fn primitives(self, vertex_count: usize) -> impl Iterator<Item = Primitive> {
+        let primitive_count = match self {
+            PrimitiveTopology::PointList => vertex_count,
+            PrimitiveTopology::LineList => vertex_count / 2,
+            PrimitiveTopology::LineStrip => vertex_count.saturating_sub(1),
+            PrimitiveTopology::TriangleList => vertex_count / 3,
+            PrimitiveTopology::TriangleStrip | PrimitiveTopology::TriangleFan => {
+                vertex_count.saturating_sub(2)
+            }
+        };
+        (0..primitive_count).map(move |i| match self {
+            PrimitiveTopology::PointList => Primitive::Point(i),
+            PrimitiveTopology::LineList => Primitive::Line([2 * i, 2 * i + 1]),
+            PrimitiveTopology::LineStrip => Primitive::Line([i, i + 1]),
+            PrimitiveTopology::TriangleList => Primitive::Triangle([3 * i, 3 * i + 1, 3 * i + 2]),
+            PrimitiveTopology::TriangleStrip if i % 2 == 1 => {
+                Primitive::Triangle([i + 1, i, i + 2])
+            }
+            PrimitiveTopology::TriangleStrip => Primitive::Triangle([i, i + 1, i + 2]),
+            PrimitiveTopology::TriangleFan => Primitive::Triangle([0, i + 1, i + 2]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_keep_a_consistent_winding() {
+        assert_eq!(
+            PrimitiveTopology::TriangleStrip
+                .primitives(5)
+                .collect::<Vec<_>>(),
+            [
+                Primitive::Triangle([0, 1, 2]),
+                Primitive::Triangle([2, 1, 3]),
+                Primitive::Triangle([2, 3, 4]),
+            ]
+        );
+    }
+
+    #[test]
+    fn incomplete_primitives_are_dropped() {
+        assert_eq!(PrimitiveTopology::LineList.primitives(3).count(), 1);
+        assert_eq!(PrimitiveTopology::LineStrip.primitives(1).count(), 0);
+        assert_eq!(PrimitiveTopology::TriangleList.primitives(5).count(), 1);
+        assert_eq!(PrimitiveTopology::TriangleFan.primitives(2).count(), 0);
+    }
+}
@@ -0,0 +1,102 @@
+use nalgebra_glm::{TVec, Vec3};
+
+/// Attributes that can be interpolated across a primitive.
+///
+/// Only a linear combination has to be provided; every interpolation used by the pipeline is
+/// built on top of it.
+pub trait Varyings: Copy {
+    fn linear_combination(&self, a: f32, other: &Self, b: f32) -> Self;
+
+    fn scale(&self, a: f32) -> Self {
+        self.linear_combination(a, self, 0.0)
+    }
+
+    fn difference(&self, other: &Self) -> Self {
+        self.linear_combination(1.0, other, -1.0)
+    }
+
+    fn lerp(&self, other: &Self, a: f32) -> Self {
+        self.linear_combination(1.0 - a, other, a)
+    }
+
+    fn bary_lerp(&self, v1: &Self, v2: &Self, t: Vec3) -> Self {
+        self.linear_combination(t.x, v1, t.y)
+            .linear_combination(1.0, v2, t.z)
+    }
+
+    /// Interpolates with screen-space barycentric coordinates `t`, given the inverse of the `w`
+    /// coordinate of each vertex.
+    fn perspective_bary_lerp(&self, v1: &Self, v2: &Self, t: Vec3, w_inv: Vec3) -> Self {
+        let t = t.component_mul(&w_inv);
+        self.bary_lerp(v1, v2, t / (t.x + t.y + t.z))
+    }
+}
+
+impl Varyings for () {
+    fn linear_combination(&self, _: f32, _: &Self, _: f32) -> Self {}
+}
+
+impl Varyings for f32 {
+    fn linear_combination(&self, a: f32, other: &Self, b: f32) -> Self {
+        a * self + b * other
+    }
+}
+
+impl<const D: usize> Varyings for TVec<f32, D> {
+    fn linear_combination(&self, a: f32, other: &Self, b: f32) -> Self {
+        a * self + b * other
+    }
+}
+
+impl<T: Varyings, const N: usize> Varyings for [T; N] {
+    fn linear_combination(&self, a: f32, other: &Self, b: f32) -> Self {
+        std::array::from_fn(|i| self[i].linear_combination(a, &other[i], b))
+    }
+}
+
+macro_rules! impl_varyings_for_tuple {
+    ($($name:ident: $index:tt),+) => {
+        impl<$($name: Varyings),+> Varyings for ($($name,)+) {
+            fn linear_combination(&self, a: f32, other: &Self, b: f32) -> Self {
+                ($(self.$index.linear_combination(a, &other.$index, b),)+)
+            }
+        }
+    };
+}
+
+impl_varyings_for_tuple!(A: 0);
+impl_varyings_for_tuple!(A: 0, B: 1);
+impl_varyings_for_tuple!(A: 0, B: 1, C: 2);
+impl_varyings_for_tuple!(A: 0, B: 1, C: 2, D: 3);
+impl_varyings_for_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4);
+impl_varyings_for_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+
+#[cfg(test)]
+mod tests {
+    use nalgebra_glm::{vec2, vec3};
+
+    use super::*;
+
+    #[test]
+    fn tuples_are_interpolated_per_element() {
+        let a = (0.0, vec2(0.0, 2.0), [1.0, 1.0]);
+        let b = (1.0, vec2(2.0, 0.0), [3.0, 5.0]);
+
+        assert_eq!(a.lerp(&b, 0.5), (0.5, vec2(1.0, 1.0), [2.0, 3.0]));
+    }
+
+    #[test]
+    fn perspective_interpolation_weights_by_inverse_w() {
+        let (v0, v1, v2) = (0.0, 1.0, 0.0);
+        let t = vec3(0.5, 0.5, 0.0);
+
+        assert_eq!(
+            v0.perspective_bary_lerp(&v1, &v2, t, vec3(1.0, 1.0, 1.0)),
+            0.5
+        );
+        assert_eq!(
+            v0.perspective_bary_lerp(&v1, &v2, t, vec3(1.0, 0.25, 1.0)),
+            0.2
+        );
+    }
+}
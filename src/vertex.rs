@@ -1,15 +1,13 @@
-use std::ops::{Div, Mul};
+use nalgebra_glm::{Mat3, Mat4, Vec2, Vec3, Vec4};
 
-use nalgebra_glm::{Mat4, Vec2, Vec3, Vec4};
-use simba::scalar::{ClosedAdd, ClosedMul};
+use crate::{color::Color, varyings::Varyings};
 
-use crate::color::Color;
-
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Vertex {
     pub coords: Vec4,
     pub color: Color,
     pub uv: Vec2,
+    pub normal: Vec3,
 }
 
 impl Vertex {
@@ -18,70 +16,103 @@ impl Vertex {
             coords: coords.push(1.0),
             color,
             uv,
+            normal: Vec3::zeros(),
         }
     }
 
+    pub fn with_normal(mut self, normal: Vec3) -> Self {
+        self.normal = normal;
+        self
+    }
+
     pub fn transform(mut self, transform: &Mat4) -> Self {
         self.coords = transform * self.coords;
         self
     }
 
-    pub fn lerp(&self, y: &Self, a: f32) -> Self {
-        use nalgebra_glm::lerp;
-        Self {
-            coords: lerp(&self.coords, &y.coords, a),
-            color: lerp(&self.color, &y.color, a),
-            uv: lerp(&self.uv, &y.uv, a),
-        }
+    /// Transforms the position, and the normal by `normal_matrix`, usually the inverse transpose
+    /// of the linear part of `transform`, computed once for all the vertices of a draw.
+    pub fn transform_with_normal_matrix(mut self, transform: &Mat4, normal_matrix: &Mat3) -> Self {
+        self.coords = transform * self.coords;
+        self.normal = normal_matrix * self.normal;
+        self
     }
+}
 
-    pub fn bary_lerp(&self, v1: &Self, v2: &Self, t: Vec3) -> Self {
-        let v0 = self;
-        let coords = bary_lerp(v0.coords, v1.coords, v2.coords, t);
-        let w0 = v0.coords.w;
-        let w1 = v1.coords.w;
-        let w2 = v2.coords.w;
-        let w_t = coords.w;
+impl Varyings for Vertex {
+    fn linear_combination(&self, a: f32, other: &Self, b: f32) -> Self {
         Self {
-            coords,
-            color: bary_lerp_perp(v0.color, w0, v1.color, w1, v2.color, w2, t, w_t),
-            uv: bary_lerp_perp(v0.uv, w0, v1.uv, w1, v2.uv, w2, t, w_t),
+            coords: self.coords.linear_combination(a, &other.coords, b),
+            color: self.color.linear_combination(a, &other.color, b),
+            uv: self.uv.linear_combination(a, &other.uv, b),
+            normal: self.normal.linear_combination(a, &other.normal, b),
         }
     }
+}
+
+/// Output of a vertex shader: a position in clip space and the attributes to be interpolated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipVertex<V> {
+    pub position: Vec4,
+    pub varyings: V,
+}
+
+impl<V: Varyings> ClipVertex<V> {
+    pub fn new(position: Vec4, varyings: V) -> Self {
+        Self { position, varyings }
+    }
 
-    pub fn duv(&self, v1: &Self, v2: &Self, t: Vec3, dt: Vec3) -> Vec2 {
-        let v0 = self;
-        let w0 = v0.coords.w;
-        let w1 = v1.coords.w;
-        let w2 = v2.coords.w;
-        let w_t0 = bary_lerp(v0.coords.w, v1.coords.w, v2.coords.w, t);
-        let w_t1 = bary_lerp(v0.coords.w, v1.coords.w, v2.coords.w, t + dt);
-        return bary_lerp_perp(v0.uv, w0, v1.uv, w1, v2.uv, w2, t + dt, w_t1)
-            - bary_lerp_perp(v0.uv, w0, v1.uv, w1, v2.uv, w2, t, w_t0);
+    pub fn lerp(&self, y: &Self, a: f32) -> Self {
+        Self {
+            position: self.position.lerp(&y.position, a),
+            varyings: self.varyings.lerp(&y.varyings, a),
+        }
     }
 
+    /// Divides the position by `w`, storing `1 / w` in its place for perspective-correct
+    /// interpolation.
     pub fn homogenize(mut self) -> Self {
-        let w_inv = 1.0 / self.coords.w;
-        self.coords *= w_inv;
-        self.coords.w = w_inv;
+        let w_inv = 1.0 / self.position.w;
+        self.position *= w_inv;
+        self.position.w = w_inv;
         self
     }
 }
 
-#[inline]
-fn bary_lerp<T>(v0: T, v1: T, v2: T, t: Vec3) -> T
-where
-    f32: Mul<T, Output = T>,
-    T: ClosedAdd,
-{
-    t.x * v0 + t.y * v1 + t.z * v2
+/// Interpolates a homogenized triangle at the screen-space barycentric coordinates `t`.
+pub fn interpolate_triangle<V: Varyings>(triangle: &[ClipVertex<V>; 3], t: Vec3) -> ClipVertex<V> {
+    let [v0, v1, v2] = triangle;
+    let w_inv = Vec3::new(v0.position.w, v1.position.w, v2.position.w);
+    ClipVertex {
+        position: v0.position.bary_lerp(&v1.position, &v2.position, t),
+        varyings: v0
+            .varyings
+            .perspective_bary_lerp(&v1.varyings, &v2.varyings, t, w_inv),
+    }
 }
 
-#[inline]
-fn bary_lerp_perp<T>(v0: T, w0: f32, v1: T, w1: f32, v2: T, w2: f32, t: Vec3, w_t: f32) -> T
-where
-    f32: Mul<T, Output = T> + ClosedMul,
-    T: ClosedAdd + Div<f32, Output = T>,
-{
-    bary_lerp(w0 * v0, w1 * v1, w2 * v2, t) / w_t
+#[cfg(test)]
+mod tests {
+    use nalgebra_glm::{scaling, vec2, vec3};
+
+    use super::*;
+    use crate::color::WHITE;
+
+    #[test]
+    fn normals_stay_perpendicular_under_non_uniform_scaling() {
+        let transform = scaling(&vec3(2.0, 1.0, 1.0));
+        let normal_matrix = Mat3::from(transform.fixed_view::<3, 3>(0, 0))
+            .try_inverse()
+            .unwrap()
+            .transpose();
+        // A normal of the plane x + y = 1, which becomes x / 2 + y = 1.
+        let vertex = Vertex::new(vec3(1.0, 0.0, 0.0), WHITE, vec2(0.0, 0.0))
+            .with_normal(vec3(1.0, 1.0, 0.0));
+
+        let transformed = vertex.transform_with_normal_matrix(&transform, &normal_matrix);
+
+        assert_eq!(transformed.coords, vec3(2.0, 0.0, 0.0).push(1.0));
+        assert_eq!(transformed.normal, vec3(0.5, 1.0, 0.0));
+        assert_eq!(vertex.transform(&transform).normal, vertex.normal);
+    }
 }
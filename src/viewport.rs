@@ -6,6 +6,8 @@ pub struct Viewport {
     pub y: f32,
     pub width: f32,
     pub height: f32,
+    pub min_depth: f32,
+    pub max_depth: f32,
 }
 
 impl Viewport {
@@ -15,9 +17,17 @@ impl Viewport {
             y,
             width,
             height,
+            min_depth: 0.0,
+            max_depth: 1.0,
         }
     }
 
+    pub fn with_depth_range(mut self, min_depth: f32, max_depth: f32) -> Self {
+        self.min_depth = min_depth;
+        self.max_depth = max_depth;
+        self
+    }
+
     pub fn full(width: f32, height: f32) -> Self {
         Self::new(0.0, 0.0, width, height)
     }
@@ -28,6 +38,47 @@ impl Viewport {
             (src.y + 1.0) * self.height / 2.0 + self.y,
         )
     }
+
+    pub fn ndc_depth_to_framebuffer(&self, depth: f32) -> f32 {
+        self.min_depth + depth * (self.max_depth - self.min_depth)
+    }
+}
+
+/// Axis-aligned rectangle of pixels in the framebuffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub const fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        Rect::new(x, y, right.saturating_sub(x), bottom.saturating_sub(y))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    pub fn contains(&self, coords: (usize, usize)) -> bool {
+        (self.x..self.x + self.width).contains(&coords.0)
+            && (self.y..self.y + self.height).contains(&coords.1)
+    }
 }
 
 #[cfg(test)]
@@ -52,4 +103,22 @@ mod tests {
             vec2(800.0, 560.0)
         );
     }
+
+    #[test]
+    pub fn ndc_depth_to_framebuffer() {
+        let viewport = Viewport::full(640.0, 480.0).with_depth_range(1.0, 0.0);
+        assert_eq!(viewport.ndc_depth_to_framebuffer(0.0), 1.0);
+        assert_eq!(viewport.ndc_depth_to_framebuffer(0.25), 0.75);
+        assert_eq!(viewport.ndc_depth_to_framebuffer(1.0), 0.0);
+    }
+
+    #[test]
+    pub fn rect_intersection() {
+        let a = Rect::new(0, 0, 4, 4);
+        assert_eq!(
+            a.intersection(&Rect::new(2, 1, 4, 2)),
+            Rect::new(2, 1, 2, 2)
+        );
+        assert!(a.intersection(&Rect::new(5, 0, 4, 4)).is_empty());
+    }
 }
@@ -0,0 +1,310 @@
+//! Renders reference scenes headlessly and compares them against the images checked in under
+//! `tests/golden`. Run with `BLESS=1` to overwrite the references with the current output.
+
+use std::{f32::consts::PI, path::PathBuf};
+
+use nalgebra_glm::{vec2, vec3, Mat4, Vec3};
+use rasterization_in_a_weekend::{
+    color::to_raw_color,
+    color::{Color, ColorSpace, BLACK, BLUE, GREEN, RED, WHITE},
+    framebuffer::Framebuffer,
+    image::Image,
+    mipmap::MipmapFilter,
+    model::unit_cube_indexed,
+    multisample::SampleCount,
+    pipeline::RasterizationPipeline,
+    sampler::{AddressMode, Filter, MipmapMode, Sampler},
+    shader::{FlatColorShader, TextureShader, TransformShader, VertexColorShader},
+    state::{BlendState, CullMode, DepthStencilState, FrontFace, RasterizerState},
+    topology::PrimitiveTopology,
+    vertex::Vertex,
+    viewport::Viewport,
+};
+
+const WIDTH: usize = 128;
+const HEIGHT: usize = 96;
+
+/// Largest difference allowed in any channel of a pixel, out of 255.
+const TOLERANCE: f32 = 2.0;
+
+fn reference_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.png"))
+}
+
+fn check_golden(name: &str, framebuffer: &Framebuffer) {
+    let actual = framebuffer.to_image();
+    let path = reference_path(name);
+    if std::env::var_os("BLESS").is_some() {
+        actual.save(&path).unwrap();
+        return;
+    }
+    let reference = Image::from_file(path.clone())
+        .unwrap_or_else(|error| {
+            panic!(
+                "cannot load {}: {error}; run with BLESS=1 to create it",
+                path.display()
+            )
+        })
+        .with_color_space(actual.color_space());
+    assert_eq!(
+        (actual.width(), actual.height()),
+        (reference.width(), reference.height()),
+        "{name}: size differs from the reference"
+    );
+
+    let mut mismatches = 0;
+    let mut diff = Vec::with_capacity(actual.width() * actual.height());
+    for y in 0..actual.height() {
+        for x in 0..actual.width() {
+            let expected = reference.get_color((x, y));
+            let error = (actual.get_color((x, y)) - expected).abs().max() * 255.0;
+            if error > TOLERANCE {
+                mismatches += 1;
+                diff.push(0xFFFF0000);
+            } else {
+                let gray = (expected.xyz().dot(&vec3(0.3, 0.59, 0.11)) * 64.0) as u32;
+                diff.push(0xFF000000 | gray << 16 | gray << 8 | gray);
+            }
+        }
+    }
+    if mismatches == 0 {
+        return;
+    }
+
+    let output = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    std::fs::create_dir_all(&output).unwrap();
+    let actual_path = output.join(format!("{name}-actual.png"));
+    let diff_path = output.join(format!("{name}-diff.png"));
+    actual.save(&actual_path).unwrap();
+    Image::from_buffer(diff, actual.width(), actual.height())
+        .save(&diff_path)
+        .unwrap();
+    panic!(
+        "{name}: {mismatches} pixels differ from the reference by more than {TOLERANCE}/255; \
+         see {} and {}",
+        actual_path.display(),
+        diff_path.display()
+    );
+}
+
+fn projection() -> Mat4 {
+    let projection =
+        nalgebra_glm::perspective_fov_rh_zo(PI / 3.0, WIDTH as f32, HEIGHT as f32, 0.1, 100.0);
+    let view =
+        nalgebra_glm::look_at_rh(&Vec3::zeros(), &vec3(0.0, 0.0, 1.0), &vec3(0.0, -1.0, 0.0));
+    projection * view
+}
+
+fn pipeline() -> RasterizationPipeline {
+    RasterizationPipeline::new(Viewport::full(WIDTH as f32, HEIGHT as f32))
+}
+
+#[test]
+fn textured_cube() {
+    let image =
+        Image::from_file(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("textures/historical.jpg"))
+            .unwrap();
+    let sampler = Sampler::new(
+        AddressMode::Clamp,
+        AddressMode::Clamp,
+        Filter::Linear,
+        Filter::Linear,
+    );
+    let mut uv = [
+        vec2(0.0, 0.0),
+        vec2(0.0, 1.0),
+        vec2(1.0, 1.0),
+        vec2(1.0, 0.0),
+    ]
+    .into_iter()
+    .cycle();
+    let (vertices, indices) = unit_cube_indexed(|_, c| Vertex::new(c, WHITE, uv.next().unwrap()));
+    let world = nalgebra_glm::rotate_y(
+        &nalgebra_glm::rotate_x(
+            &nalgebra_glm::translate(&nalgebra_glm::identity(), &vec3(0.0, 0.0, 2.5)),
+            PI / 5.0,
+        ),
+        PI / 7.0,
+    );
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT).with_color_space(ColorSpace::Srgb);
+
+    framebuffer.clear(BLACK, f32::INFINITY);
+    pipeline().draw_indexed(
+        &mut framebuffer,
+        PrimitiveTopology::TriangleList,
+        (
+            &TransformShader::new(projection() * world),
+            &TextureShader::new(&image, &sampler),
+        ),
+        &vertices,
+        &indices,
+    );
+
+    check_golden("textured_cube", &framebuffer);
+}
+
+/// Draws a receding floor textured with a checkerboard.
+fn render_floor(min_filter: Filter) -> Framebuffer {
+    // A 64x64 checkerboard of 4x4 texel squares, repeated eight times across a receding floor.
+    let buffer = (0..64 * 64)
+        .map(|i: usize| {
+            let color = if ((i % 64) / 4 + i / 64 / 4) % 2 == 0 {
+                WHITE
+            } else {
+                RED
+            };
+            to_raw_color(color)
+        })
+        .collect();
+    let image = Image::from_buffer(buffer, 64, 64)
+        .with_color_space(ColorSpace::Srgb)
+        .with_mipmaps(MipmapFilter::Kaiser);
+    let sampler = Sampler::new(
+        AddressMode::Clamp,
+        AddressMode::Clamp,
+        min_filter,
+        Filter::Linear,
+    )
+    .with_mipmap_mode(MipmapMode::Linear);
+    let vertices = [
+        vec3(-3.0, 1.0, 0.5),
+        vec3(3.0, 1.0, 0.5),
+        vec3(3.0, 1.0, 20.0),
+        vec3(-3.0, 1.0, 20.0),
+    ]
+    .map(|c| Vertex::new(c, WHITE, vec2(c.x / 6.0 + 0.5, c.z / 20.0)));
+    let mut pipeline = pipeline();
+    pipeline.set_rasterizer_state(RasterizerState::new(
+        CullMode::None,
+        FrontFace::CounterClockwise,
+    ));
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT).with_color_space(ColorSpace::Srgb);
+
+    framebuffer.clear(BLACK, f32::INFINITY);
+    pipeline.draw_indexed(
+        &mut framebuffer,
+        PrimitiveTopology::TriangleList,
+        (
+            &TransformShader::new(projection()),
+            &TextureShader::new(&image, &sampler),
+        ),
+        &vertices,
+        &[0u32, 1, 2, 0, 2, 3],
+    );
+    framebuffer
+}
+
+#[test]
+fn trilinear_floor() {
+    check_golden("trilinear_floor", &render_floor(Filter::Linear));
+}
+
+#[test]
+fn anisotropic_floor() {
+    check_golden("anisotropic_floor", &render_floor(Filter::Anisotropic(4)));
+}
+
+#[test]
+fn near_plane_clipping() {
+    // A floor stretching from behind the camera into the distance.
+    let vertices = [
+        vec3(-4.0, 1.0, -2.0),
+        vec3(4.0, 1.0, -2.0),
+        vec3(0.0, 1.0, 30.0),
+        vec3(-1.0, -1.0, 3.0),
+        vec3(1.0, 0.5, 0.05),
+        vec3(1.5, -1.0, 5.0),
+    ]
+    .into_iter()
+    .zip([RED, GREEN, BLUE, WHITE, RED, GREEN])
+    .map(|(c, color)| Vertex::new(c, color, c.xy()))
+    .collect::<Vec<_>>();
+    let mut pipeline = pipeline();
+    pipeline.set_rasterizer_state(RasterizerState::new(
+        CullMode::None,
+        FrontFace::CounterClockwise,
+    ));
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+
+    framebuffer.clear(BLACK, f32::INFINITY);
+    pipeline.draw(
+        &mut framebuffer,
+        PrimitiveTopology::TriangleList,
+        (&TransformShader::new(projection()), &VertexColorShader),
+        &vertices,
+    );
+
+    check_golden("near_plane_clipping", &framebuffer);
+}
+
+#[test]
+fn multisampled_blending() {
+    let triangle = |offset: Vec3, color: Color| {
+        [
+            vec3(-0.8, -0.6, 0.5),
+            vec3(0.1, 0.9, 0.5),
+            vec3(0.7, -0.8, 0.5),
+        ]
+        .map(|c| Vertex::new(c + offset, color, c.xy()))
+    };
+    let vertices = [
+        triangle(Vec3::zeros(), Color::new(1.0, 0.2, 0.1, 0.8)),
+        triangle(vec3(0.3, 0.1, 0.0), Color::new(0.1, 0.4, 1.0, 0.5)),
+    ]
+    .concat();
+    let mut pipeline = pipeline();
+    pipeline.set_rasterizer_state(RasterizerState::new(
+        CullMode::None,
+        FrontFace::CounterClockwise,
+    ));
+    pipeline.set_blend_state(BlendState::alpha_blending());
+    pipeline.set_depth_stencil_state(DepthStencilState::disabled());
+    let mut framebuffer = Framebuffer::new_multisampled(WIDTH, HEIGHT, SampleCount::X4);
+
+    framebuffer.clear(BLACK, f32::INFINITY);
+    pipeline.draw(
+        &mut framebuffer,
+        PrimitiveTopology::TriangleList,
+        (
+            &TransformShader::new(nalgebra_glm::identity()),
+            &VertexColorShader,
+        ),
+        &vertices,
+    );
+
+    check_golden("multisampled_blending", &framebuffer);
+}
+
+#[test]
+fn lines_and_points() {
+    let vertices = (0..12)
+        .map(|i| {
+            let angle = i as f32 * PI / 6.0;
+            let radius = if i % 2 == 0 { 0.9 } else { 0.4 };
+            let c = vec3(radius * angle.cos(), radius * angle.sin(), 0.5);
+            Vertex::new(c, WHITE, c.xy())
+        })
+        .collect::<Vec<_>>();
+    let transform = TransformShader::new(nalgebra_glm::identity());
+    let mut pipeline = pipeline();
+    pipeline.set_point_size(3.0);
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+
+    framebuffer.clear(BLACK, f32::INFINITY);
+    pipeline.draw(
+        &mut framebuffer,
+        PrimitiveTopology::LineStrip,
+        (&transform, &FlatColorShader::new(GREEN)),
+        &vertices,
+    );
+    pipeline.draw(
+        &mut framebuffer,
+        PrimitiveTopology::PointList,
+        (&transform, &FlatColorShader::new(RED)),
+        &vertices,
+    );
+
+    check_golden("lines_and_points", &framebuffer);
+}